@@ -0,0 +1,246 @@
+use crate::export::numbered_path;
+use crate::images::grace_days;
+use crate::retention::{encode_attachment_name, file_name, move_to_trash, orphaned_attachments, ATTACHMENT_URL_PREFIX, DAY};
+use crate::storage::{ensure_attachments_dir, get_attachments_dir};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::AppHandle;
+
+/// How much of a file is read to detect its type
+const SNIFF_LEN: usize = 512;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Attachment {
+    /// Stored file name: the original one, with a counter added if it was taken
+    pub name: String,
+    pub size: u64,
+    pub mime: String,
+    /// Link to insert into the note
+    pub markdown: String,
+}
+
+/// Leading bytes of common binary formats
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"Rar!\x1a\x07", "application/vnd.rar"),
+    (b"{\\rtf", "application/rtf"),
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF8", "image/gif"),
+    (b"ID3", "audio/mpeg"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"\x1a\x45\xdf\xa3", "video/webm"),
+];
+
+fn mime_from_extension(extension: &str) -> Option<&'static str> {
+    let mime = match extension.to_ascii_lowercase().as_str() {
+        "txt" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "tsv" => "text/tab-separated-values",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "pdf" => "application/pdf",
+        "rtf" => "application/rtf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "7z" => "application/x-7z-compressed",
+        "rar" => "application/vnd.rar",
+        "doc" => "application/msword",
+        "xls" => "application/vnd.ms-excel",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+        "epub" => "application/epub+zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+fn mime_from_bytes(head: &[u8]) -> Option<&'static str> {
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return Some(mime);
+    }
+    match (head.get(..4), head.get(8..12)) {
+        (Some(b"RIFF"), Some(b"WEBP")) => return Some("image/webp"),
+        (Some(b"RIFF"), Some(b"WAVE")) => return Some("audio/wav"),
+        _ => {}
+    }
+    if head.get(4..8) == Some(b"ftyp") {
+        return Some("video/mp4");
+    }
+    None
+}
+
+/// MIME type from the file's leading bytes, with the extension refining containers
+/// such as ZIP (DOCX, EPUB) and naming text formats
+pub fn mime_type(path: &Path) -> String {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    if let Ok(file) = File::open(path) {
+        let _ = file.take(SNIFF_LEN as u64).read_to_end(&mut head);
+    }
+    let by_extension = path.extension().and_then(|ext| ext.to_str()).and_then(mime_from_extension);
+
+    let mime = match mime_from_bytes(&head) {
+        Some("application/zip") => by_extension.unwrap_or("application/zip"),
+        Some(mime) => mime,
+        // Text has no signature; anything without NUL bytes that decodes counts
+        None if !head.contains(&0) && std::str::from_utf8(&head).is_ok() => by_extension.unwrap_or("text/plain"),
+        None => by_extension.unwrap_or("application/octet-stream"),
+    };
+    mime.to_string()
+}
+
+/// Keep only the final path component and replace characters some filesystems reject
+fn sanitize_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_end_matches('.');
+
+    if name.is_empty() {
+        "attachment".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Create a new file at `base`, or at the first free numbered variant of it. `create_new`
+/// claims the name atomically, so two attachments with the same name never share a file.
+fn create_unique(base: PathBuf) -> io::Result<(PathBuf, File)> {
+    let mut path = base.clone();
+    let mut counter = 1;
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                path = numbered_path(&base, counter);
+                counter += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Store a file under its original name, made unique, using `write` to fill it in
+fn store(app: &AppHandle, filename: &str, write: impl FnOnce(&mut File) -> io::Result<()>) -> Result<Attachment, String> {
+    let attachments_dir = ensure_attachments_dir(app)?;
+    let (path, mut file) = create_unique(attachments_dir.join(sanitize_filename(filename))).map_err(|e| e.to_string())?;
+    if let Err(e) = write(&mut file) {
+        drop(file);
+        let _ = fs::remove_file(&path);
+        return Err(e.to_string());
+    }
+
+    let name = file_name(&path);
+    let label = name.replace('[', "\\[").replace(']', "\\]");
+    Ok(Attachment {
+        size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+        mime: mime_type(&path),
+        markdown: format!("[{}]({}{})", label, ATTACHMENT_URL_PREFIX, encode_attachment_name(&name)),
+        name,
+    })
+}
+
+/// The stored attachment called `name`, refusing anything outside the attachments directory
+fn attachment_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let path = get_attachments_dir(app).join(name);
+    if name.is_empty() || file_name(&path) != name || name == ".." {
+        return Err(format!("Invalid attachment name: {}", name));
+    }
+    if !path.is_file() {
+        return Err(format!("Attachment not found: {}", name));
+    }
+    Ok(path)
+}
+
+/// Store file contents sent from the frontend, e.g. a dropped or pasted file
+#[tauri::command]
+pub async fn save_attachment(app: AppHandle, filename: String, data: Vec<u8>) -> Result<Attachment, String> {
+    store(&app, &filename, |file| file.write_all(&data))
+}
+
+/// Copy a file from disk into the attachments, e.g. one dropped onto a window
+#[tauri::command]
+pub async fn attach_file(app: AppHandle, path: PathBuf) -> Result<Attachment, String> {
+    let filename = file_name(&path);
+    let mut source = File::open(&path).map_err(|e| e.to_string())?;
+    store(&app, &filename, |target| io::copy(&mut source, target).map(|_| ()))
+}
+
+/// Open an attachment with the system's default application for its type
+#[tauri::command]
+pub async fn open_attachment(app: AppHandle, name: String) -> Result<(), String> {
+    let path = attachment_path(&app, &name)?;
+
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("explorer").arg(&path).spawn().map_err(|e| e.to_string())?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open").arg(&path).spawn().map_err(|e| e.to_string())?;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("xdg-open").arg(&path).spawn().map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Move the orphaned attachments the user confirmed from a report to the trash.
+/// Each one is checked again, like images.
+#[tauri::command]
+pub async fn trash_orphaned_attachments(app: AppHandle, names: Vec<String>) -> Result<Vec<String>, String> {
+    let orphans = orphaned_attachments(&app, DAY * grace_days(&app));
+    let mut moved = Vec::new();
+
+    for path in orphans.iter().filter(|path| names.contains(&file_name(path))) {
+        if let Err(e) = move_to_trash(&app, path) {
+            log::warn!("Failed to move orphaned attachment {} to trash: {}", path.display(), e);
+            continue;
+        }
+        moved.push(file_name(path));
+    }
+
+    if !moved.is_empty() {
+        log::info!("Moved orphaned attachments to trash: {:?}", moved);
+    }
+    Ok(moved)
+}
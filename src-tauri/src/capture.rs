@@ -0,0 +1,249 @@
+use crate::inbox::AppendTarget;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+/// Most capture windows that can be open at once, not counting the pre-warmed one
+const MAX_CAPTURE_WINDOWS: usize = 5;
+
+/// Delay before building the next warm window, so it doesn't compete with the one being shown
+const PREWARM_DELAY: Duration = Duration::from_millis(500);
+
+static WINDOW_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// What a capture window does with its text
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum CaptureMode {
+    /// Create a new, empty note
+    #[serde(rename = "new")]
+    NewNote,
+    /// Create a new note pre-filled from a template
+    Template { name: String },
+    /// Append a timestamped entry to an existing note instead of creating one
+    Append { target: AppendTarget },
+    /// Continue editing a note the backend already created, e.g. from a selection
+    Existing { note: String },
+}
+
+#[derive(Default)]
+struct WindowSlot {
+    /// The window's frontend is listening for activation
+    ready: bool,
+    /// Request handed out before the frontend was ready
+    pending: Option<CaptureMode>,
+    /// Note the window is editing, so it can be cleaned up at shutdown
+    note: Option<String>,
+    /// When the window was handed out; unset while it's the warm window
+    shown: Option<Instant>,
+}
+
+#[derive(Default)]
+struct PoolInner {
+    /// Hidden, fully loaded window waiting for the next capture
+    warm: Option<String>,
+    /// A warm window is being built
+    warming: bool,
+    /// Every live capture window, warm or in use
+    windows: HashMap<String, WindowSlot>,
+}
+
+impl PoolInner {
+    fn active_count(&self) -> usize {
+        self.windows.len() - usize::from(self.warm.is_some())
+    }
+
+    /// Label of the capture window that has been open the longest
+    fn oldest(&self) -> Option<String> {
+        self.windows
+            .iter()
+            .filter_map(|(label, slot)| slot.shown.map(|shown| (shown, label)))
+            .min()
+            .map(|(_, label)| label.clone())
+    }
+}
+
+/// Keeps one hidden capture window ready so the capture shortcut only has to show it
+#[derive(Default)]
+pub struct CapturePool {
+    inner: Mutex<PoolInner>,
+}
+
+impl CapturePool {
+    fn lock(&self) -> MutexGuard<'_, PoolInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn next_label() -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    format!("capture-{}-{}", millis, WINDOW_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Build a hidden capture window; it stays idle until it receives a `CaptureMode`
+fn build_capture_window(app: &AppHandle, label: &str) -> tauri::Result<WebviewWindow> {
+    let url = WebviewUrl::App("index.html?window=capture".into());
+
+    let builder = WebviewWindowBuilder::new(app, label, url)
+        .title("Quick Capture")
+        .inner_size(500.0, 400.0)
+        .min_inner_size(300.0, 200.0)
+        .center()
+        .resizable(true)
+        .decorations(false)
+        .visible(false);
+
+    // Transparent windows work on Windows and macOS (with macos-private-api feature)
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    let builder = builder.transparent(true).shadow(false);
+
+    builder.build()
+}
+
+/// Build the next warm window unless one is already ready or being built
+pub fn prewarm(app: &AppHandle) {
+    let pool = app.state::<CapturePool>();
+    {
+        let mut inner = pool.lock();
+        if inner.warm.is_some() || inner.warming {
+            return;
+        }
+        inner.warming = true;
+    }
+
+    let label = next_label();
+    let built = build_capture_window(app, &label);
+
+    let mut inner = pool.lock();
+    inner.warming = false;
+    match built {
+        Ok(_) => {
+            inner.windows.insert(label.clone(), WindowSlot::default());
+            inner.warm = Some(label);
+        }
+        Err(e) => log::error!("Failed to pre-warm capture window: {}", e),
+    }
+}
+
+/// Pre-warm from a background thread after a short delay
+pub fn schedule_prewarm(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(PREWARM_DELAY);
+        prewarm(&app);
+    });
+}
+
+/// Show a capture window for `mode`, using the warm window when there is one
+pub fn open_capture(app: &AppHandle, mode: CaptureMode) {
+    let pool = app.state::<CapturePool>();
+
+    let warm = {
+        let mut inner = pool.lock();
+        if inner.active_count() >= MAX_CAPTURE_WINDOWS {
+            // Bring back the oldest one rather than ignoring the shortcut
+            log::warn!("Capture window limit ({}) reached", MAX_CAPTURE_WINDOWS);
+            if let Some(window) = inner.oldest().and_then(|label| app.get_webview_window(&label)) {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            return;
+        }
+        inner
+            .warm
+            .take()
+            .and_then(|label| app.get_webview_window(&label))
+    };
+
+    // Cold path: nothing warm yet (first launch, or captures in quick succession)
+    let window = match warm {
+        Some(window) => window,
+        None => {
+            let label = next_label();
+            match build_capture_window(app, &label) {
+                Ok(window) => {
+                    pool.lock().windows.insert(label, WindowSlot::default());
+                    window
+                }
+                Err(e) => {
+                    log::error!("Failed to create capture window: {}", e);
+                    return;
+                }
+            }
+        }
+    };
+
+    let deliver_now = {
+        let mut inner = pool.lock();
+        let slot = inner.windows.entry(window.label().to_string()).or_default();
+        slot.shown = Some(Instant::now());
+        if slot.ready {
+            true
+        } else {
+            slot.pending = Some(mode.clone());
+            false
+        }
+    };
+
+    if deliver_now {
+        if let Err(e) = app.emit_to(window.label(), "capture-activate", &mode) {
+            log::error!("Failed to activate capture window: {}", e);
+        }
+    }
+
+    let _ = window.center();
+    let _ = window.show();
+    let _ = window.set_focus();
+
+    schedule_prewarm(app);
+}
+
+/// Drop a destroyed window from the pool, replacing it if it was the warm one
+pub fn forget_window(app: &AppHandle, label: &str) {
+    let pool = app.state::<CapturePool>();
+    let was_warm = {
+        let mut inner = pool.lock();
+        inner.windows.remove(label);
+        if inner.warm.as_deref() == Some(label) {
+            inner.warm = None;
+            true
+        } else {
+            false
+        }
+    };
+
+    if was_warm {
+        schedule_prewarm(app);
+    }
+}
+
+/// Notes being edited in open capture windows
+pub fn open_notes(app: &AppHandle) -> Vec<String> {
+    let pool = app.state::<CapturePool>();
+    let inner = pool.lock();
+    inner.windows.values().filter_map(|slot| slot.note.clone()).collect()
+}
+
+/// Called by a capture window once it knows which note it is editing
+#[tauri::command]
+pub fn capture_note_created(window: WebviewWindow, pool: State<'_, CapturePool>, id: String) {
+    let mut inner = pool.lock();
+    inner.windows.entry(window.label().to_string()).or_default().note = Some(id);
+}
+
+/// Called by a capture window once it listens for `capture-activate`.
+/// Returns the request if the window was handed out before it finished loading.
+#[tauri::command]
+pub fn capture_ready(window: WebviewWindow, pool: State<'_, CapturePool>) -> Option<CaptureMode> {
+    let mut inner = pool.lock();
+    let slot = inner.windows.entry(window.label().to_string()).or_default();
+    slot.ready = true;
+    slot.pending.take()
+}
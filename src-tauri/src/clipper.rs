@@ -1,14 +1,25 @@
+use crate::attachments::mime_type;
+use crate::commands::write_new_note;
+use crate::images::ImageIndex;
+use crate::markdown::{clean_markdown, html_to_markdown, markdown_to_html};
+use crate::retention::IMAGE_URL_PREFIX;
+use crate::rtf::rtf_to_markdown;
+use crate::storage::{get_images_dir, NoteMeta};
 use arboard::Clipboard;
-use html2md::parse_html;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, RgbaImage};
+use tauri::{AppHandle, Emitter, Manager};
 
 #[cfg(target_os = "windows")]
 use std::ffi::OsStr;
 #[cfg(target_os = "windows")]
 use std::os::windows::ffi::OsStrExt;
 
-/// Read HTML from Windows clipboard
+/// Read the raw bytes of a registered Windows clipboard format, e.g. "HTML Format"
 #[cfg(target_os = "windows")]
-fn get_clipboard_html() -> Option<String> {
+fn get_clipboard_format(name: &str) -> Option<Vec<u8>> {
     use windows::Win32::System::DataExchange::*;
     use windows::Win32::System::Memory::*;
     use windows::Win32::Foundation::*;
@@ -19,25 +30,22 @@ fn get_clipboard_html() -> Option<String> {
             return None;
         }
 
-        // Register HTML format
-        let format_name: Vec<u16> = OsStr::new("HTML Format")
+        // Register the format by name
+        let format_name: Vec<u16> = OsStr::new(name)
             .encode_wide()
             .chain(std::iter::once(0))
             .collect();
-        let html_format = RegisterClipboardFormatW(PCWSTR(format_name.as_ptr()));
+        let format = RegisterClipboardFormatW(PCWSTR(format_name.as_ptr()));
 
-        let result = if html_format != 0 {
-            let handle = GetClipboardData(html_format);
+        let result = if format != 0 {
+            let handle = GetClipboardData(format);
             if let Ok(h) = handle {
                 let ptr = GlobalLock(HGLOBAL(h.0)) as *const u8;
                 if !ptr.is_null() {
                     let size = GlobalSize(HGLOBAL(h.0));
-                    let slice = std::slice::from_raw_parts(ptr, size);
-                    // HTML Format has a header, find the actual HTML
-                    let content = String::from_utf8_lossy(slice);
-                    let html = extract_html_from_cf_html(&content);
+                    let data = std::slice::from_raw_parts(ptr, size).to_vec();
                     let _ = GlobalUnlock(HGLOBAL(h.0));
-                    html
+                    Some(data)
                 } else {
                     None
                 }
@@ -53,112 +61,688 @@ fn get_clipboard_html() -> Option<String> {
     }
 }
 
+/// Read HTML from Windows clipboard, still wrapped in its CF_HTML header
+#[cfg(target_os = "windows")]
+fn get_clipboard_html() -> Option<String> {
+    let data = get_clipboard_format("HTML Format")?;
+    Some(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// Read HTML from the clipboard: `public.html` on macOS and the `text/html` target on X11
+/// and Wayland, the latter through the data-control protocol
 #[cfg(not(target_os = "windows"))]
 fn get_clipboard_html() -> Option<String> {
+    Clipboard::new().ok()?.get().html().ok()
+}
+
+/// Keep clipboard data only if it really is RTF; some tools fall back to plain text
+fn rtf_only(data: Vec<u8>) -> Option<Vec<u8>> {
+    data.starts_with(b"{\\rtf").then_some(data)
+}
+
+#[cfg(target_os = "windows")]
+fn get_clipboard_rtf() -> Option<Vec<u8>> {
+    get_clipboard_format("Rich Text Format").and_then(rtf_only)
+}
+
+/// How long a clipboard or window tool gets to answer; they can hang on an unresponsive owner
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+const CLIPBOARD_TOOL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Run a clipboard or window tool and return what it printed, killing it if it takes too long
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn clipboard_tool_output(command: &mut std::process::Command) -> Option<Vec<u8>> {
+    use std::io::Read;
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null()).spawn().ok()?;
+    // Read on another thread, so a full pipe can't stall the tool while we wait for it
+    let mut stdout = child.stdout.take()?;
+    let reader = std::thread::spawn(move || {
+        let mut data = Vec::new();
+        stdout.read_to_end(&mut data).map(|_| data)
+    });
+
+    let deadline = Instant::now() + CLIPBOARD_TOOL_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            _ => {
+                log::warn!("Clipboard tool {:?} did not finish, giving up", command.get_program());
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+
+    let data = reader.join().ok()?.ok()?;
+    status.success().then_some(data)
+}
+
+/// arboard has no RTF support; `pbpaste` prints the plain text instead when there is no RTF
+#[cfg(target_os = "macos")]
+fn get_clipboard_rtf() -> Option<Vec<u8>> {
+    clipboard_tool_output(std::process::Command::new("pbpaste").args(["-Prefer", "rtf"])).and_then(rtf_only)
+}
+
+/// Through `wl-paste` on Wayland and `xclip` on X11, when they are installed
+#[cfg(target_os = "linux")]
+fn get_clipboard_rtf() -> Option<Vec<u8>> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    ["text/rtf", "application/rtf"].into_iter().find_map(|target| {
+        let mut command = if wayland {
+            let mut command = std::process::Command::new("wl-paste");
+            command.args(["--no-newline", "--type", target]);
+            command
+        } else {
+            let mut command = std::process::Command::new("xclip");
+            command.args(["-selection", "clipboard", "-target", target, "-out"]);
+            command
+        };
+        clipboard_tool_output(&mut command).and_then(rtf_only)
+    })
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn get_clipboard_rtf() -> Option<Vec<u8>> {
     None
 }
 
-/// Extract actual HTML from CF_HTML format (which has headers)
-fn extract_html_from_cf_html(cf_html: &str) -> Option<String> {
+/// Plain text currently on the clipboard, if any
+pub fn get_clipboard_text() -> Option<String> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .ok()
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// Image currently on the clipboard, encoded as PNG
+pub fn get_clipboard_png() -> Result<Option<Vec<u8>>, String> {
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    let image = match clipboard.get_image() {
+        Ok(image) => image,
+        Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let pixels = RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.into_owned())
+        .ok_or("Clipboard image has an unexpected size")?;
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(pixels)
+        .write_with_encoder(PngEncoder::new(&mut png))
+        .map_err(|e| e.to_string())?;
+    Ok(Some(png))
+}
+
+/// Store the clipboard image through the image pipeline and return a Markdown link to it,
+/// or `None` when the clipboard holds no image
+pub fn store_clipboard_image(app: &AppHandle) -> Result<Option<String>, String> {
+    let Some(png) = get_clipboard_png()? else {
+        return Ok(None);
+    };
+    let name = app.state::<ImageIndex>().store(app, &png)?;
+    Ok(Some(format!("![Pasted image]({}{})", IMAGE_URL_PREFIX, name)))
+}
+
+/// Read the text selected in the focused application from the PRIMARY selection
+#[cfg(target_os = "linux")]
+pub fn get_selected_text() -> Option<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+
+    let mut clipboard = Clipboard::new().ok()?;
+    clipboard
+        .get()
+        .clipboard(LinuxClipboardKind::Primary)
+        .text()
+        .ok()
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// What `get_selected_text` keeps of the clipboard while it copies the selection over it
+#[cfg(not(target_os = "linux"))]
+struct SavedClipboard {
+    text: Option<String>,
+    html: Option<String>,
+    image: Option<arboard::ImageData<'static>>,
+}
+
+#[cfg(not(target_os = "linux"))]
+impl SavedClipboard {
+    fn save(clipboard: &mut Clipboard) -> Self {
+        SavedClipboard {
+            text: clipboard.get_text().ok(),
+            // Windows hands back CF_HTML, which `set_html` would wrap a second time
+            html: clipboard.get().html().ok().and_then(|raw| extract_html_fragment(&raw)),
+            image: clipboard.get_image().ok(),
+        }
+    }
+
+    /// Put the saved content back. The clipboard takes one kind at a time here, so HTML
+    /// (with its text) wins over an image, which wins over text alone.
+    fn restore(self, clipboard: &mut Clipboard) {
+        let restored = match (self.html, self.image, self.text) {
+            (Some(html), _, text) => clipboard.set_html(html, text),
+            (None, Some(image), _) => clipboard.set_image(image),
+            (None, None, Some(text)) => clipboard.set_text(text),
+            (None, None, None) => Ok(()),
+        };
+        if let Err(e) = restored {
+            log::warn!("Failed to restore the clipboard after copying the selection: {}", e);
+        }
+    }
+}
+
+/// Read the text selected in the focused application by copying it, then restore the clipboard
+#[cfg(not(target_os = "linux"))]
+pub fn get_selected_text() -> Option<String> {
+    let mut clipboard = Clipboard::new().ok()?;
+    let previous = SavedClipboard::save(&mut clipboard);
+    let _ = clipboard.clear();
+
+    send_copy_keystroke();
+
+    // The target app copies asynchronously; poll briefly for the new content
+    let mut selected = None;
+    for _ in 0..15 {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        if let Ok(text) = clipboard.get_text() {
+            if !text.trim().is_empty() {
+                selected = Some(text);
+                break;
+            }
+        }
+    }
+
+    previous.restore(&mut clipboard);
+    selected
+}
+
+/// Send Ctrl+C to the focused window, releasing Alt from the capture shortcut first
+#[cfg(target_os = "windows")]
+fn send_copy_keystroke() {
+    use windows::Win32::UI::Input::KeyboardAndMouse::*;
+
+    let key = |vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: 0,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+
+    let inputs = [
+        key(VK_MENU, KEYEVENTF_KEYUP),
+        key(VK_CONTROL, KEYBD_EVENT_FLAGS(0)),
+        key(VK_C, KEYBD_EVENT_FLAGS(0)),
+        key(VK_C, KEYEVENTF_KEYUP),
+        key(VK_CONTROL, KEYEVENTF_KEYUP),
+    ];
+
+    unsafe {
+        SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+    }
+}
+
+/// Send Cmd+C to the frontmost application
+#[cfg(target_os = "macos")]
+fn send_copy_keystroke() {
+    let _ = std::process::Command::new("osascript")
+        .args(["-e", "tell application \"System Events\" to keystroke \"c\" using command down"])
+        .status();
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn send_copy_keystroke() {}
+
+/// Name of the application that currently has focus, when the platform exposes it
+#[cfg(target_os = "windows")]
+pub fn frontmost_app_name() -> Option<String> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::*;
+    use windows::Win32::UI::WindowsAndMessaging::*;
+
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid as *mut u32));
+        if pid == 0 {
+            return None;
+        }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 260];
+        let mut size = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size);
+        let _ = CloseHandle(process);
+        result.ok()?;
+
+        let path = String::from_utf16_lossy(&buffer[..size as usize]);
+        std::path::Path::new(&path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn frontmost_app_name() -> Option<String> {
+    let output = clipboard_tool_output(std::process::Command::new("osascript").args([
+        "-e",
+        "tell application \"System Events\" to get name of first application process whose frontmost is true",
+    ]))?;
+    let name = String::from_utf8_lossy(&output).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+/// X11 only; Wayland compositors don't expose the focused window to other clients
+#[cfg(target_os = "linux")]
+pub fn frontmost_app_name() -> Option<String> {
+    let output = clipboard_tool_output(std::process::Command::new("xdotool").args(["getactivewindow", "getwindowclassname"]))?;
+    let name = String::from_utf8_lossy(&output).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn frontmost_app_name() -> Option<String> {
+    None
+}
+
+/// Value of a CF_HTML header field such as `StartFragment` or `SourceURL`.
+/// Only the header lines before the markup are searched.
+fn cf_html_header<'a>(cf_html: &'a str, key: &str) -> Option<&'a str> {
     // CF_HTML format has headers like:
     // Version:0.9
     // StartHTML:0000000105
     // EndHTML:0000000253
     // StartFragment:0000000141
     // EndFragment:0000000217
+    // SourceURL:https://example.com/
     // <html>...</html>
+    if !cf_html.starts_with("Version:") {
+        return None;
+    }
+    let header = &cf_html[..cf_html.find('<').unwrap_or(cf_html.len())];
+    header.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim())
+    })
+}
+
+/// Byte offset of `needle` in `haystack`, ignoring ASCII case
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(&needle.to_ascii_lowercase())
+}
+
+/// Reduce clipboard HTML to the copied fragment. Each platform wraps it differently:
+/// Windows adds a CF_HTML header with byte offsets, browsers add `<!--StartFragment-->`
+/// markers, and macOS and Linux apps often add a BOM, a leading `<meta charset>` tag or a
+/// whole document around it.
+fn extract_html_fragment(raw: &str) -> Option<String> {
+    let raw = raw.trim_start_matches('\u{feff}').trim_end_matches('\0');
+
+    // Browsers mark the fragment explicitly. Prefer that to the CF_HTML offsets, which some
+    // apps count in characters rather than bytes.
+    if let Some(start) = raw.find("<!--StartFragment-->") {
+        let rest = &raw[start + "<!--StartFragment-->".len()..];
+        if let Some(end) = rest.find("<!--EndFragment-->") {
+            return Some(rest[..end].to_string());
+        }
+    }
 
-    // Try to find StartFragment/EndFragment for selection
-    let start_frag = cf_html.find("StartFragment:")
-        .and_then(|i| {
-            let rest = &cf_html[i + 14..];
-            rest.lines().next()?.trim().parse::<usize>().ok()
-        });
-    let end_frag = cf_html.find("EndFragment:")
-        .and_then(|i| {
-            let rest = &cf_html[i + 12..];
-            rest.lines().next()?.trim().parse::<usize>().ok()
-        });
-
-    if let (Some(start), Some(end)) = (start_frag, end_frag) {
-        if end > start && end <= cf_html.len() {
-            return Some(cf_html[start..end].to_string());
+    // CF_HTML offsets count bytes from the start of the header
+    let offset = |key| cf_html_header(raw, key)?.parse::<usize>().ok();
+    if let (Some(start), Some(end)) = (offset("StartFragment"), offset("EndFragment")) {
+        if let Some(fragment) = raw.get(start..end).filter(|_| end > start) {
+            return Some(fragment.to_string());
         }
     }
 
-    // Fallback: find <html> or first <
-    if let Some(start) = cf_html.find("<html") {
-        return Some(cf_html[start..].to_string());
+    // Offsets missing or wrong: drop the header and fall back to the markup
+    let mut html = if raw.starts_with("Version:") { &raw[raw.find('<')?..] } else { raw };
+
+    if let Some(body) = find_ignore_case(html, "<body") {
+        let rest = &html[body..];
+        let start = rest.find('>').map_or(rest.len(), |i| i + 1);
+        let end = find_ignore_case(rest, "</body").unwrap_or(rest.len()).max(start);
+        html = &rest[start..end];
     }
-    if let Some(start) = cf_html.find('<') {
-        return Some(cf_html[start..].to_string());
+
+    // Chrome on macOS and Linux puts a bare `<meta charset='utf-8'>` before the fragment
+    html = html.trim_start();
+    while find_ignore_case(html, "<meta") == Some(0) {
+        html = html[html.find('>').map_or(html.len(), |i| i + 1)..].trim_start();
     }
 
-    None
+    (!html.trim().is_empty()).then(|| html.to_string())
 }
 
-/// Read HTML from clipboard, convert to Markdown, and write back to clipboard
-#[tauri::command]
-pub fn clip_to_markdown() -> Result<String, String> {
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+/// Opening tags named `name` (e.g. `<meta ...>`) with their byte offsets, in document order
+fn find_tags<'a>(html: &'a str, name: &str) -> Vec<(usize, &'a str)> {
+    let lower = html.to_ascii_lowercase();
+    let needle = format!("<{}", name);
+    let mut tags = Vec::new();
+    let mut from = 0;
 
-    // Try to read HTML from clipboard first
-    let html = get_clipboard_html();
+    while let Some(i) = lower[from..].find(&needle) {
+        let start = from + i;
+        let after = start + needle.len();
+        from = after;
+        if !matches!(lower.as_bytes().get(after), Some(b' ' | b'\t' | b'\r' | b'\n' | b'/' | b'>')) {
+            continue;
+        }
+        let end = lower[after..].find('>').map_or(html.len(), |i| after + i + 1);
+        tags.push((start, &html[start..end]));
+    }
+    tags
+}
 
-    // Also get plain text as fallback
-    let plain_text = clipboard.get_text().ok();
+/// Value of attribute `name` in an opening tag, quoted or not
+fn tag_attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
 
-    let markdown = if let Some(html_content) = html {
-        if !html_content.trim().is_empty() {
-            // Convert HTML to Markdown
-            let md = parse_html(&html_content);
-            clean_markdown(&md)
-        } else if let Some(text) = plain_text {
-            // Fallback to plain text
-            text
-        } else {
-            return Err("Clipboard is empty".to_string());
+    while let Some(i) = lower[from..].find(name) {
+        let start = from + i;
+        from = start + name.len();
+        let preceded = lower[..start].ends_with([' ', '\t', '\r', '\n']);
+        let rest = lower[from..].trim_start();
+        if !preceded || !rest.starts_with('=') {
+            continue;
+        }
+
+        let value = tag[tag.len() - rest.len() + 1..].trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split([' ', '\t', '\r', '\n', '>']).next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value.trim()));
+    }
+    None
+}
+
+/// Decode the character references that show up in titles and URLs
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Text inside the first of the given elements, tags removed and whitespace collapsed
+fn first_element_text(html: &str, names: &[&str]) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let (start, name) = names
+        .iter()
+        .filter_map(|name| find_tags(html, name).first().map(|(start, tag)| (start + tag.len(), *name)))
+        .min()?;
+    let end = lower[start..].find(&format!("</{}", name)).map_or(html.len(), |i| start + i);
+
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html[start..end].chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
         }
-    } else if let Some(text) = plain_text {
-        // No HTML, use plain text
-        text
-    } else {
-        return Err("Clipboard is empty".to_string());
+    }
+    let text = decode_entities(&text).split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Address of the page the HTML was copied from. Windows puts it in the CF_HTML
+/// `SourceURL` header; elsewhere only documents carrying a `<base>`, canonical link
+/// or `og:url` tag reveal it.
+fn html_source_url(raw: &str) -> Option<String> {
+    let from_header = cf_html_header(raw, "SourceURL").map(str::to_string);
+    let from_base = || find_tags(raw, "base").into_iter().find_map(|(_, tag)| tag_attribute(tag, "href"));
+    let from_link = || {
+        find_tags(raw, "link")
+            .into_iter()
+            .filter(|(_, tag)| tag_attribute(tag, "rel").is_some_and(|rel| rel.eq_ignore_ascii_case("canonical")))
+            .find_map(|(_, tag)| tag_attribute(tag, "href"))
     };
+    let from_meta = || {
+        find_tags(raw, "meta")
+            .into_iter()
+            .filter(|(_, tag)| tag_attribute(tag, "property").as_deref() == Some("og:url"))
+            .find_map(|(_, tag)| tag_attribute(tag, "content"))
+    };
+
+    from_header
+        .or_else(from_base)
+        .or_else(from_link)
+        .or_else(from_meta)
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+}
+
+/// Clipboard contents converted to Markdown, with what is known about where they came from
+pub struct Clip {
+    pub markdown: String,
+    /// Page the HTML was copied from
+    pub source_url: Option<String>,
+    /// The fragment's first heading, or the page's `<title>`
+    pub title: Option<String>,
+}
+
+impl Clip {
+    /// Note text: the title as a heading unless the clip already starts with it,
+    /// then the clip and a source line
+    pub fn note_content(&self) -> String {
+        let mut content = String::new();
+        if let Some(title) = &self.title {
+            let first_line = self.markdown.lines().next().unwrap_or_default();
+            if first_line.trim_start_matches('#').trim() != title {
+                content.push_str(&format!("# {}\n\n", title));
+            }
+        }
+        content.push_str(self.markdown.trim_end());
+        content.push('\n');
+        if let Some(url) = &self.source_url {
+            content.push_str(&format!("\nSource: {}\n", url));
+        }
+        content
+    }
+}
+
+/// Read the clipboard as Markdown: converted from HTML or RTF when there is some, plain text otherwise
+fn read_clip(clipboard: &mut Clipboard) -> Result<Clip, String> {
+    if let Some(raw) = get_clipboard_html() {
+        if let Some(fragment) = extract_html_fragment(&raw) {
+            let markdown = clean_markdown(&html_to_markdown(&fragment));
+            if !markdown.is_empty() {
+                return Ok(Clip {
+                    markdown,
+                    source_url: html_source_url(&raw),
+                    title: first_element_text(&fragment, &["h1", "h2", "h3", "h4", "h5", "h6"])
+                        .or_else(|| first_element_text(&raw, &["title"])),
+                });
+            }
+        }
+    }
+
+    // Word processors, Outlook and TextEdit often offer RTF but no HTML
+    if let Some(rtf) = get_clipboard_rtf() {
+        let markdown = clean_markdown(&rtf_to_markdown(&rtf));
+        if !markdown.is_empty() {
+            return Ok(Clip { markdown, source_url: None, title: None });
+        }
+    }
+
+    let text = clipboard.get_text().ok().filter(|text| !text.trim().is_empty());
+    let markdown = text.ok_or("Clipboard is empty")?;
+    Ok(Clip { markdown, source_url: None, title: None })
+}
+
+/// Read HTML from clipboard, convert to Markdown, and write back to clipboard
+pub fn convert_clipboard() -> Result<String, String> {
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    let clip = read_clip(&mut clipboard)?;
 
     // Write markdown back to clipboard
     clipboard
-        .set_text(&markdown)
+        .set_text(&clip.markdown)
         .map_err(|e| e.to_string())?;
 
-    Ok(markdown)
+    Ok(clip.markdown)
+}
+
+/// Convert the clipboard to Markdown and save it as a new note, titled after the page
+/// and ending with a line naming its source
+pub fn clip_note(app: &AppHandle) -> Result<NoteMeta, String> {
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    let clip = read_clip(&mut clipboard)?;
+
+    let note = write_new_note(app, &clip.note_content())?;
+    let _ = app.emit("notes-changed", ());
+    Ok(note)
 }
 
-/// Clean up the converted markdown
-fn clean_markdown(md: &str) -> String {
-    let mut result = md.to_string();
+/// Convert the clipboard in place; async, since reading it may wait on external tools
+#[tauri::command]
+pub async fn clip_to_markdown() -> Result<String, String> {
+    convert_clipboard()
+}
+
+/// Save the clipboard as a new note; async for the same reason
+#[tauri::command]
+pub async fn clip_to_note(app: AppHandle) -> Result<NoteMeta, String> {
+    clip_note(&app)
+}
 
-    // Remove excessive blank lines (more than 2 consecutive)
-    while result.contains("\n\n\n") {
-        result = result.replace("\n\n\n", "\n\n");
+/// A stored image as a `data:` URI, so it survives being pasted outside Gravity
+fn image_data_uri(app: &AppHandle, name: &str) -> Option<String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
     }
+    let path = get_images_dir(app).join(name);
+    let data = std::fs::read(&path).ok()?;
+    Some(format!("data:{};base64,{}", mime_type(&path), STANDARD.encode(data)))
+}
 
-    // Trim leading/trailing whitespace from each line while preserving structure
-    let lines: Vec<&str> = result.lines().collect();
-    let cleaned: Vec<String> = lines
-        .iter()
-        .map(|line| {
-            // Preserve indentation for code blocks and lists
-            if line.starts_with("    ") || line.starts_with("\t") || line.starts_with("- ") || line.starts_with("* ") || line.starts_with("> ") {
-                line.trim_end().to_string()
-            } else {
-                line.trim().to_string()
-            }
-        })
-        .collect();
+/// Copy Markdown (a whole note or a selection from it) as rich text: rendered HTML, with
+/// the Markdown itself as the plain-text alternative. Stored images are inlined as data
+/// URIs when `inline_images` is set and left out otherwise.
+#[tauri::command]
+pub async fn copy_as_rich_text(app: AppHandle, markdown: String, inline_images: bool) -> Result<(), String> {
+    let html = markdown_to_html(&markdown, |url| match url.strip_prefix(IMAGE_URL_PREFIX) {
+        Some(name) if inline_images => image_data_uri(&app, name),
+        Some(_) => None,
+        None => Some(url.to_string()),
+    });
+
+    // arboard adds the CF_HTML header on Windows and the document wrapper elsewhere
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    clipboard
+        .set_html(html, Some(markdown))
+        .map_err(|e| e.to_string())
+}
+
+/// Store the clipboard image and return a Markdown image link, or `None` without one
+#[tauri::command]
+pub async fn paste_clipboard_image(app: AppHandle) -> Result<Option<String>, String> {
+    store_clipboard_image(&app)
+}
 
-    result = cleaned.join("\n");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Trim overall
-    result.trim().to_string()
+    /// A Windows CF_HTML header. Every field is fixed width, so its length doesn't depend on the offsets.
+    fn cf_html_header_for(start_fragment: usize, end_fragment: usize) -> String {
+        format!(
+            "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\nSourceURL:https://example.com/\r\n",
+            0, 0, start_fragment, end_fragment
+        )
+    }
+
+    /// Wrap `fragment` in a CF_HTML document with correct byte offsets
+    fn cf_html(before: &str, fragment: &str, after: &str) -> String {
+        let start = cf_html_header_for(0, 0).len() + before.len();
+        let end = start + fragment.len();
+        format!("{}{}{}{}", cf_html_header_for(start, end), before, fragment, after)
+    }
+
+    /// The markup Chrome and Edge put around a copied fragment
+    fn chrome_cf_html(fragment: &str) -> String {
+        cf_html("<html>\r\n<body>\r\n<!--StartFragment-->", fragment, "<!--EndFragment-->\r\n</body>\r\n</html>")
+    }
+
+    #[test]
+    fn cf_html_with_valid_offsets() {
+        let raw = chrome_cf_html("<p>Hello <b>world</b></p>");
+        assert_eq!(extract_html_fragment(&raw).as_deref(), Some("<p>Hello <b>world</b></p>"));
+
+        // Word and older apps leave out the markers, so only the offsets find the fragment
+        let raw = cf_html("<html><body><div class=\"WordSection1\">", "<p>Hello</p>", "</div></body></html>");
+        assert_eq!(extract_html_fragment(&raw).as_deref(), Some("<p>Hello</p>"));
+    }
+
+    #[test]
+    fn cf_html_with_wrong_offsets() {
+        // Past the end: fall back to the markers
+        let raw = chrome_cf_html("<p>Hello</p>").replacen("EndFragment:00", "EndFragment:99", 1);
+        assert_eq!(extract_html_fragment(&raw).as_deref(), Some("<p>Hello</p>"));
+
+        // Pointing at the wrong text: the markers still win
+        let raw = chrome_cf_html("<p>Hello</p>").replacen("StartFragment:00000", "StartFragment:00001", 1);
+        assert_eq!(extract_html_fragment(&raw).as_deref(), Some("<p>Hello</p>"));
+
+        // Out of range with no markers either: fall back to the body
+        let raw = format!("{}<html><body><p>Hello</p></body></html>", cf_html_header_for(500, 600));
+        assert_eq!(extract_html_fragment(&raw).as_deref(), Some("<p>Hello</p>"));
+    }
+
+    #[test]
+    fn cf_html_with_multibyte_text_near_offsets() {
+        let raw = chrome_cf_html("<p>Grüße, 世界 👋</p>");
+        assert_eq!(extract_html_fragment(&raw).as_deref(), Some("<p>Grüße, 世界 👋</p>"));
+
+        let raw = cf_html("<html><body>", "世界👋", "</body></html>");
+        assert_eq!(extract_html_fragment(&raw).as_deref(), Some("世界👋"));
+
+        // Offsets inside a character, as from an app counting characters, must not panic
+        let header = cf_html_header_for(0, 0).len() + "<html><body>".len();
+        let raw = format!("{}<html><body>世界👋</body></html>", cf_html_header_for(header + 1, header + 5));
+        assert_eq!(extract_html_fragment(&raw).as_deref(), Some("世界👋"));
+    }
+
+    #[test]
+    fn start_fragment_markers() {
+        let raw = "<html><body>\n<!--StartFragment--><ul><li>one</li></ul><!--EndFragment-->\n</body></html>";
+        assert_eq!(extract_html_fragment(raw).as_deref(), Some("<ul><li>one</li></ul>"));
+    }
+
+    #[test]
+    fn macos_document_with_bom() {
+        let raw = "\u{feff}<!DOCTYPE html>\n<html><head><meta charset=\"UTF-8\"><title>Notes</title></head>\n<BODY class=\"x\">\n<p>Copied from Safari</p>\n</BODY></html>\0";
+        assert_eq!(extract_html_fragment(raw).as_deref(), Some("<p>Copied from Safari</p>\n"));
+    }
+
+    #[test]
+    fn chrome_meta_charset_prefix() {
+        let raw = "<meta charset='utf-8'><meta name=\"generator\" content=\"x\"> <h1>Title</h1><p>Text</p>";
+        assert_eq!(extract_html_fragment(raw).as_deref(), Some("<h1>Title</h1><p>Text</p>"));
+    }
+
+    #[test]
+    fn empty_fragment() {
+        assert_eq!(extract_html_fragment("<meta charset='utf-8'>  "), None);
+        assert_eq!(extract_html_fragment("<html><body>\n</body></html>"), None);
+    }
 }
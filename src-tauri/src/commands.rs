@@ -1,18 +1,30 @@
+use crate::images::ImageIndex;
+use crate::note_state::{self, NoteState, NoteStates};
+use crate::retention;
+use crate::shutdown::Shutdown;
+use crate::tray;
+use crate::writer::WriteQueue;
 use crate::storage::{
-    self, count_words, ensure_images_dir, ensure_notes_dir, extract_preview, extract_title,
-    generate_image_filename, generate_note_filename, get_images_dir, is_note_empty, NoteMeta,
+    self, count_words, ensure_notes_dir, extract_preview, extract_title, generate_note_filename,
+    get_images_dir, is_note_empty, NoteMeta,
 };
-use std::fs;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
 use std::time::UNIX_EPOCH;
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
 
-#[tauri::command]
-pub async fn create_note(app: AppHandle) -> Result<NoteMeta, String> {
-    let notes_dir = ensure_notes_dir(&app)?;
+fn first_image(content: &str) -> Option<String> {
+    retention::image_references(content).next().map(String::from)
+}
+
+/// Write a new note with the given initial content and return its metadata
+pub(crate) fn write_new_note(app: &AppHandle, content: &str) -> Result<NoteMeta, String> {
+    let notes_dir = ensure_notes_dir(app)?;
     let filename = generate_note_filename();
     let path = notes_dir.join(&filename);
 
-    fs::write(&path, "").map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| e.to_string())?;
 
     let id = filename.trim_end_matches(".md").to_string();
     let now = chrono::Local::now().to_rfc3339();
@@ -20,49 +32,73 @@ pub async fn create_note(app: AppHandle) -> Result<NoteMeta, String> {
     Ok(NoteMeta {
         id,
         path: path.to_string_lossy().to_string(),
-        title: "Untitled".to_string(),
-        preview: String::new(),
+        title: extract_title(content),
+        preview: extract_preview(content),
         created_at: now.clone(),
         modified_at: now,
-        word_count: 0,
-        char_count: 0,
+        word_count: count_words(content),
+        char_count: content.chars().count(),
+        pinned: false,
+        starred: false,
+        archived: false,
+        last_opened: None,
+        image: first_image(content),
     })
 }
 
+#[tauri::command]
+pub async fn create_note(app: AppHandle) -> Result<NoteMeta, String> {
+    write_new_note(&app, "")
+}
+
 #[tauri::command]
 pub async fn save_note(app: AppHandle, id: String, content: String) -> Result<NoteMeta, String> {
-    let notes_dir = ensure_notes_dir(&app)?;
-    let path = notes_dir.join(format!("{}.md", id));
+    // Shutdown waits for saves in progress
+    let shutdown = app.state::<Shutdown>();
+    let _saving = shutdown.track_save();
 
-    fs::write(&path, &content).map_err(|e| e.to_string())?;
+    app.state::<WriteQueue>().save_now(&app, &id, &content)
+}
 
-    let metadata = fs::metadata(&path).map_err(|e| e.to_string())?;
-    let _modified = metadata
-        .modified()
-        .map_err(|e| e.to_string())?
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| e.to_string())?;
+/// Write a note in place and make sure it reached the disk
+pub(crate) fn write_note(notes_dir: &Path, id: &str, content: &str) -> Result<NoteMeta, String> {
+    let path = notes_dir.join(format!("{}.md", id));
 
-    Ok(NoteMeta {
-        id,
+    let mut file = File::create(&path).map_err(|e| e.to_string())?;
+    file.write_all(content.as_bytes()).map_err(|e| e.to_string())?;
+    file.sync_data().map_err(|e| e.to_string())?;
+
+    let mut note = NoteMeta {
+        id: id.to_string(),
         path: path.to_string_lossy().to_string(),
-        title: extract_title(&content),
-        preview: extract_preview(&content),
+        title: extract_title(content),
+        preview: extract_preview(content),
         created_at: String::new(), // Will be filled by frontend
         modified_at: chrono::Local::now().to_rfc3339(),
-        word_count: count_words(&content),
+        word_count: count_words(content),
         char_count: content.chars().count(),
-    })
+        pinned: false,
+        starred: false,
+        archived: false,
+        last_opened: None,
+        image: first_image(content),
+    };
+    apply_state(&mut note, &note_state::get_state(notes_dir, id));
+    Ok(note)
 }
 
 #[tauri::command]
 pub async fn delete_note(app: AppHandle, id: String) -> Result<(), String> {
     let notes_dir = storage::get_notes_dir(&app);
     let path = notes_dir.join(format!("{}.md", id));
-
-    if path.exists() {
-        fs::remove_file(&path).map_err(|e| e.to_string())?;
-    }
+    // Deleted notes wait in the trash until the retention policy purges them
+    app.state::<WriteQueue>().delete(&id, || {
+        if path.exists() {
+            retention::move_to_trash(&app, &path)?;
+        }
+        Ok(true)
+    })?;
+    note_state::remove_state(&notes_dir, &id)?;
     Ok(())
 }
 
@@ -74,10 +110,74 @@ pub async fn get_note(app: AppHandle, id: String) -> Result<String, String> {
     fs::read_to_string(&path).map_err(|e| e.to_string())
 }
 
-#[tauri::command]
-pub async fn list_notes(app: AppHandle) -> Result<Vec<NoteMeta>, String> {
-    let notes_dir = ensure_notes_dir(&app)?;
+/// Copy a note's stored flags onto its metadata
+pub(crate) fn apply_state(note: &mut NoteMeta, state: &NoteState) {
+    note.pinned = state.pinned;
+    note.starred = state.starred;
+    note.archived = state.archived;
+    note.last_opened = state.last_opened.clone();
+}
+
+/// Build metadata for a note file on disk, with its pinned, starred and archived state;
+/// `content` is the file's current text
+pub(crate) fn read_note_meta(path: &Path, content: &str) -> Result<NoteMeta, String> {
+    let mut note = read_meta_without_state(path, content)?;
+    if let Some(notes_dir) = path.parent() {
+        let state = note_state::get_state(notes_dir, &note.id);
+        apply_state(&mut note, &state);
+    }
+    Ok(note)
+}
+
+/// Metadata from the file alone, for callers that look up the states of many notes at once
+pub(crate) fn read_meta_without_state(path: &Path, content: &str) -> Result<NoteMeta, String> {
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+
+    let filename = path.file_stem().unwrap().to_string_lossy().to_string();
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| {
+            chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default();
+
+    let created = metadata
+        .created()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| {
+            chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default();
+
+    Ok(NoteMeta {
+        id: filename,
+        path: path.to_string_lossy().to_string(),
+        title: extract_title(content),
+        preview: extract_preview(content),
+        created_at: created,
+        modified_at: modified,
+        word_count: count_words(content),
+        char_count: content.chars().count(),
+        pinned: false,
+        starred: false,
+        archived: false,
+        last_opened: None,
+        image: first_image(content),
+    })
+}
+
+/// Every note with its state applied: pinned first, archived notes only when asked for
+pub(crate) fn load_notes(app: &AppHandle, include_archived: bool) -> Result<Vec<NoteMeta>, String> {
+    let notes_dir = ensure_notes_dir(app)?;
     let mut notes = Vec::new();
+    let mut contents = Vec::new();
 
     let entries = fs::read_dir(&notes_dir).map_err(|e| e.to_string())?;
 
@@ -87,53 +187,108 @@ pub async fn list_notes(app: AppHandle) -> Result<Vec<NoteMeta>, String> {
 
         if path.extension().map_or(false, |ext| ext == "md") {
             let content = fs::read_to_string(&path).unwrap_or_default();
-            let metadata = fs::metadata(&path).map_err(|e| e.to_string())?;
-
-            let filename = path.file_stem().unwrap().to_string_lossy().to_string();
-            let modified = metadata
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-                .map(|d| {
-                    chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
-                        .map(|dt| dt.to_rfc3339())
-                        .unwrap_or_default()
-                })
-                .unwrap_or_default();
-
-            let created = metadata
-                .created()
-                .ok()
-                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
-                .map(|d| {
-                    chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
-                        .map(|dt| dt.to_rfc3339())
-                        .unwrap_or_default()
-                })
-                .unwrap_or_default();
-
-            notes.push(NoteMeta {
-                id: filename,
-                path: path.to_string_lossy().to_string(),
-                title: extract_title(&content),
-                preview: extract_preview(&content),
-                created_at: created,
-                modified_at: modified,
-                word_count: count_words(&content),
-                char_count: content.chars().count(),
-            });
+            let note = read_meta_without_state(&path, &content)?;
+            contents.push((note.id.clone(), content));
+            notes.push(note);
+        }
+    }
+
+    let states = note_state::reconcile(&notes_dir, &contents);
+    for note in notes.iter_mut() {
+        if let Some(state) = states.get(&note.id) {
+            apply_state(note, state);
         }
     }
 
-    // Sort by modified date, most recent first
-    notes.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+    if !include_archived {
+        notes.retain(|note| !note.archived);
+    }
 
+    sort_notes(&mut notes, &states);
     Ok(notes)
 }
 
+/// Pinned notes first in their manual order, then by modified date, most recent first
+pub(crate) fn sort_notes(notes: &mut [NoteMeta], states: &NoteStates) {
+    let pin_order = |note: &NoteMeta| {
+        states
+            .get(&note.id)
+            .filter(|s| s.pinned)
+            .map(|s| s.pin_order.unwrap_or(u32::MAX))
+    };
+    notes.sort_by(|a, b| match (pin_order(a), pin_order(b)) {
+        (Some(x), Some(y)) => x.cmp(&y).then_with(|| b.modified_at.cmp(&a.modified_at)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => b.modified_at.cmp(&a.modified_at),
+    });
+}
+
 #[tauri::command]
-pub async fn delete_if_empty(app: AppHandle, id: String) -> Result<bool, String> {
-    let notes_dir = storage::get_notes_dir(&app);
+pub async fn list_notes(app: AppHandle, include_archived: Option<bool>) -> Result<Vec<NoteMeta>, String> {
+    load_notes(&app, include_archived.unwrap_or(false))
+}
+
+fn read_note_for_state(notes_dir: &std::path::Path, id: &str) -> Result<String, String> {
+    let path = notes_dir.join(format!("{}.md", id));
+    fs::read_to_string(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_note_pinned(app: AppHandle, id: String, pinned: bool) -> Result<NoteState, String> {
+    let notes_dir = ensure_notes_dir(&app)?;
+    let content = read_note_for_state(&notes_dir, &id)?;
+
+    let state = note_state::update_state(&notes_dir, &id, &content, |state, states| {
+        if pinned && !state.pinned {
+            state.pin_order = Some(note_state::next_pin_order(states));
+        } else if !pinned {
+            state.pin_order = None;
+        }
+        state.pinned = pinned;
+    })?;
+    tray::schedule_refresh(&app);
+    Ok(state)
+}
+
+#[tauri::command]
+pub async fn set_note_starred(app: AppHandle, id: String, starred: bool) -> Result<NoteState, String> {
+    let notes_dir = ensure_notes_dir(&app)?;
+    let content = read_note_for_state(&notes_dir, &id)?;
+
+    note_state::update_state(&notes_dir, &id, &content, |state, _| state.starred = starred)
+}
+
+#[tauri::command]
+pub async fn set_note_archived(app: AppHandle, id: String, archived: bool) -> Result<NoteState, String> {
+    let notes_dir = ensure_notes_dir(&app)?;
+    let content = read_note_for_state(&notes_dir, &id)?;
+
+    note_state::update_state(&notes_dir, &id, &content, |state, _| state.archived = archived)
+}
+
+/// Record that the user opened a note, for the tray's recent notes
+#[tauri::command]
+pub async fn mark_note_opened(app: AppHandle, id: String) -> Result<(), String> {
+    let notes_dir = ensure_notes_dir(&app)?;
+    let content = read_note_for_state(&notes_dir, &id)?;
+
+    note_state::mark_opened(&notes_dir, &id, &content)?;
+    tray::schedule_refresh(&app);
+    Ok(())
+}
+
+/// Set the manual order of pinned notes; IDs that aren't pinned are ignored
+#[tauri::command]
+pub async fn reorder_pinned_notes(app: AppHandle, ids: Vec<String>) -> Result<(), String> {
+    let notes_dir = ensure_notes_dir(&app)?;
+    note_state::reorder_pinned(&notes_dir, &ids)?;
+    tray::schedule_refresh(&app);
+    Ok(())
+}
+
+/// Delete the note if it has no content; returns whether it was deleted
+pub(crate) fn remove_if_empty(notes_dir: &Path, id: &str) -> Result<bool, String> {
     let path = notes_dir.join(format!("{}.md", id));
 
     if path.exists() {
@@ -147,53 +302,30 @@ pub async fn delete_if_empty(app: AppHandle, id: String) -> Result<bool, String>
 }
 
 #[tauri::command]
-pub async fn cleanup_empty_notes(app: AppHandle, max_age_minutes: u64) -> Result<u32, String> {
-    let notes_dir = storage::get_notes_dir(&app);
-    let mut deleted = 0;
-
-    if !notes_dir.exists() {
-        return Ok(0);
-    }
-
-    let entries = fs::read_dir(&notes_dir).map_err(|e| e.to_string())?;
-    let now = std::time::SystemTime::now();
-
-    for entry in entries {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-
-        if path.extension().map_or(false, |ext| ext == "md") {
-            let content = fs::read_to_string(&path).unwrap_or_default();
-
-            if is_note_empty(&content) {
-                if let Ok(metadata) = fs::metadata(&path) {
-                    if let Ok(modified) = metadata.modified() {
-                        if let Ok(age) = now.duration_since(modified) {
-                            if age.as_secs() > max_age_minutes * 60 {
-                                if fs::remove_file(&path).is_ok() {
-                                    deleted += 1;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+pub async fn delete_if_empty(app: AppHandle, id: String) -> Result<bool, String> {
+    // A queued edit is newer than the file on disk, so it decides
+    let queue = app.state::<WriteQueue>();
+    if let Some(content) = queue.cancel(&id) {
+        if !is_note_empty(&content) {
+            queue.save_now(&app, &id, &content)?;
+            return Ok(false);
         }
     }
-
-    Ok(deleted)
+    queue.delete(&id, || remove_if_empty(&storage::get_notes_dir(&app), &id))
 }
 
 #[tauri::command]
-pub async fn save_image(app: AppHandle, data: Vec<u8>, extension: String) -> Result<String, String> {
-    let images_dir = ensure_images_dir(&app)?;
-    let filename = generate_image_filename(&extension);
-    let path = images_dir.join(&filename);
+pub async fn cleanup_empty_notes(app: AppHandle, max_age_minutes: u64) -> Result<u32, String> {
+    let max_age = std::time::Duration::from_secs(max_age_minutes * 60);
 
-    fs::write(&path, data).map_err(|e| e.to_string())?;
+    Ok(retention::remove_empty_notes(&app, max_age, false).len() as u32)
+}
 
-    // Return the filename (not full path) for use in markdown
-    Ok(filename)
+#[tauri::command]
+pub async fn save_image(app: AppHandle, data: Vec<u8>) -> Result<String, String> {
+    // Return the filename (not full path) for use in markdown; the format comes from the
+    // bytes themselves and identical images share one file
+    app.state::<ImageIndex>().store(&app, &data)
 }
 
 #[tauri::command]
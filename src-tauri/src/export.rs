@@ -1,24 +1,16 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// Find a unique filename by adding (1), (2), etc. if file exists
-fn get_unique_path(base_path: PathBuf) -> PathBuf {
+pub(crate) fn get_unique_path(base_path: PathBuf) -> PathBuf {
     if !base_path.exists() {
         return base_path;
     }
 
-    let stem = base_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
-    let ext = base_path.extension().map(|e| e.to_string_lossy().to_string());
-    let parent = base_path.parent().unwrap_or(&base_path);
-
     let mut counter = 1;
     loop {
-        let new_name = match &ext {
-            Some(e) => format!("{} ({}).{}", stem, counter, e),
-            None => format!("{} ({})", stem, counter),
-        };
-        let new_path = parent.join(new_name);
+        let new_path = numbered_path(&base_path, counter);
         if !new_path.exists() {
             return new_path;
         }
@@ -26,6 +18,19 @@ fn get_unique_path(base_path: PathBuf) -> PathBuf {
     }
 }
 
+/// `base_path` with a counter before the extension, e.g. `notes (2).md`
+pub(crate) fn numbered_path(base_path: &Path, counter: u32) -> PathBuf {
+    let stem = base_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let ext = base_path.extension().map(|e| e.to_string_lossy().to_string());
+    let parent = base_path.parent().unwrap_or(base_path);
+
+    let new_name = match &ext {
+        Some(e) => format!("{} ({}).{}", stem, counter, e),
+        None => format!("{} ({})", stem, counter),
+    };
+    parent.join(new_name)
+}
+
 #[tauri::command]
 pub async fn export_note_file(
     content: String,
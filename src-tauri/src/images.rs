@@ -0,0 +1,442 @@
+use crate::retention::{
+    attachment_references, file_name, files_in, image_reference_spans, image_references, is_markdown, move_to_trash,
+    orphaned_attachments, orphaned_images, DAY,
+};
+use crate::settings::SettingsStore;
+use crate::ingest;
+use crate::storage::{
+    ensure_images_dir, ensure_thumbnails_dir, generate_image_filename, get_attachments_dir, get_images_dir,
+    get_notes_dir, get_templates_dir, get_thumbnails_dir, get_trash_dir,
+};
+use crate::writer::WriteQueue;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+const INDEX_FILENAME: &str = "image-index.json";
+
+/// How long the dedupe pass waits for queued note writes before rewriting notes
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Let the windows come up before decoding old images for thumbnails
+const BACKFILL_DELAY: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileUsage {
+    pub name: String,
+    pub size: u64,
+    /// Notes that refer to the file
+    pub notes: Vec<String>,
+    /// References from notes in the trash, which keep the file until they are purged
+    pub trashed_notes: usize,
+    /// Unreferenced for longer than the grace period, so it can be moved to the trash
+    pub orphaned: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImageReport {
+    pub images: Vec<FileUsage>,
+    pub attachments: Vec<FileUsage>,
+    /// Sizes cover images and attachments
+    pub total_size: u64,
+    pub orphaned_size: u64,
+    /// Unreferenced files newer than this may belong to an unsaved edit and are kept
+    pub grace_days: u32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DedupeReport {
+    /// Duplicate file name -> the identical image now used in its place
+    pub merged: BTreeMap<String, String>,
+    /// Notes whose image references were rewritten, including notes in the trash
+    pub rewritten_notes: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+type Hashes = HashMap<String, String>;
+
+/// Content hash -> file name of the stored images, so identical images are stored once.
+/// Written to `image-index.json` and rebuilt from the images directory when missing.
+#[derive(Default)]
+pub struct ImageIndex {
+    /// Loaded on first use
+    hashes: Mutex<Option<Hashes>>,
+}
+
+fn index_path(app: &AppHandle) -> PathBuf {
+    let app_data = app.path().app_data_dir().expect("Failed to get app data dir");
+    app_data.join(INDEX_FILENAME)
+}
+
+fn hash_bytes(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Hash of every image on disk by file name
+fn scan_images(images_dir: &Path) -> BTreeMap<String, String> {
+    files_in(images_dir)
+        .into_iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| Some((file_name(&path), hash_bytes(&fs::read(&path).ok()?))))
+        .collect()
+}
+
+/// Index the scanned images. Of identical images the one whose name sorts first wins;
+/// generated names are timestamps, so that's the oldest.
+fn index_scan(scanned: &BTreeMap<String, String>) -> Hashes {
+    let mut hashes = Hashes::new();
+    for (name, hash) in scanned {
+        hashes.entry(hash.clone()).or_insert_with(|| name.clone());
+    }
+    hashes
+}
+
+fn write_index(app: &AppHandle, hashes: &Hashes) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(hashes).map_err(|e| e.to_string())?;
+    let path = index_path(app);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &path).map_err(|e| e.to_string())
+}
+
+impl ImageIndex {
+    fn lock(&self) -> MutexGuard<'_, Option<Hashes>> {
+        self.hashes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn loaded<'a>(app: &AppHandle, hashes: &'a mut Option<Hashes>) -> &'a mut Hashes {
+        hashes.get_or_insert_with(|| {
+            fs::read_to_string(index_path(app))
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_else(|| index_scan(&scan_images(&get_images_dir(app))))
+        })
+    }
+
+    /// Run an image through the ingest pipeline and store it, returning the name of an
+    /// identical stored image instead when there is one. Both the incoming and the stored
+    /// bytes are indexed, so pasting the same original again skips the pipeline.
+    pub fn store(&self, app: &AppHandle, data: &[u8]) -> Result<String, String> {
+        let images_dir = ensure_images_dir(app)?;
+        let settings = app.state::<SettingsStore>().get().images;
+        let original_hash = hash_bytes(data);
+
+        let mut guard = self.lock();
+        let hashes = Self::loaded(app, &mut guard);
+
+        // The file may have left the directory without the index hearing of it
+        let stored = |hashes: &Hashes, hash: &str| hashes.get(hash).filter(|name| images_dir.join(name).is_file()).cloned();
+
+        if let Some(name) = stored(hashes, &original_hash) {
+            return Ok(name);
+        }
+
+        let processed = ingest::process(data, &settings)?;
+        let hash = hash_bytes(&processed.data);
+
+        let name = match stored(hashes, &hash) {
+            Some(name) => name,
+            None => {
+                let filename = generate_image_filename(processed.extension);
+                fs::write(images_dir.join(&filename), &processed.data).map_err(|e| e.to_string())?;
+                if let Err(e) = write_thumbnail(app, &filename, &processed.data) {
+                    log::warn!("Failed to create thumbnail for {}: {}", filename, e);
+                }
+                hashes.insert(hash, filename.clone());
+                filename
+            }
+        };
+
+        hashes.insert(original_hash, name.clone());
+        if let Err(e) = write_index(app, hashes) {
+            log::warn!("Failed to save image index: {}", e);
+        }
+        Ok(name)
+    }
+
+    /// Drop images that were moved out of the images directory
+    pub fn forget(&self, app: &AppHandle, names: &[String]) {
+        let mut guard = self.lock();
+        let Some(hashes) = guard.as_mut() else {
+            // Not loaded yet; stale entries are skipped when looked up
+            remove_thumbnails(app, names);
+            return;
+        };
+        remove_thumbnails(app, names);
+
+        let before = hashes.len();
+        hashes.retain(|_, name| !names.contains(name));
+        if hashes.len() != before {
+            if let Err(e) = write_index(app, hashes) {
+                log::warn!("Failed to save image index: {}", e);
+            }
+        }
+    }
+}
+
+fn thumbnail_path(thumbnails_dir: &Path, name: &str) -> PathBuf {
+    thumbnails_dir.join(format!("{}.png", name))
+}
+
+/// Write an image's thumbnail; returns false for images that get none, such as SVGs
+fn write_thumbnail(app: &AppHandle, name: &str, data: &[u8]) -> Result<bool, String> {
+    let Some(thumbnail) = ingest::thumbnail(data)? else {
+        return Ok(false);
+    };
+    let thumbnails_dir = ensure_thumbnails_dir(app)?;
+    fs::write(thumbnail_path(&thumbnails_dir, name), thumbnail).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+fn remove_thumbnails(app: &AppHandle, names: &[String]) {
+    let thumbnails_dir = get_thumbnails_dir(app);
+    for name in names {
+        let _ = fs::remove_file(thumbnail_path(&thumbnails_dir, name));
+    }
+}
+
+/// Create the thumbnails missing for images stored before thumbnails existed
+fn backfill_thumbnails(app: &AppHandle) {
+    let thumbnails_dir = get_thumbnails_dir(app);
+    let mut created = 0;
+
+    for path in files_in(&get_images_dir(app)).into_iter().filter(|path| path.is_file()) {
+        let name = file_name(&path);
+        if thumbnail_path(&thumbnails_dir, &name).exists() {
+            continue;
+        }
+        let result = fs::read(&path).map_err(|e| e.to_string()).and_then(|data| write_thumbnail(app, &name, &data));
+        match result {
+            Ok(true) => created += 1,
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to create thumbnail for {}: {}", name, e),
+        }
+    }
+
+    if created > 0 {
+        log::info!("Created {} missing image thumbnails", created);
+    }
+}
+
+/// Fill in missing thumbnails in the background once the app is up
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(BACKFILL_DELAY);
+        backfill_thumbnails(&app);
+    });
+}
+
+/// Point references to merged duplicates at the image kept in their place
+fn rewrite_references(content: &str, merged: &BTreeMap<String, String>) -> Option<String> {
+    let mut rewritten = String::with_capacity(content.len());
+    let mut last = 0;
+
+    for span in image_reference_spans(content) {
+        if let Some(kept) = merged.get(&content[span.clone()]) {
+            rewritten.push_str(&content[last..span.start]);
+            rewritten.push_str(kept);
+            last = span.end;
+        }
+    }
+
+    if last == 0 {
+        return None;
+    }
+    rewritten.push_str(&content[last..]);
+    Some(rewritten)
+}
+
+/// Merge identical images: rewrite every note to use one copy and move the rest to the trash
+pub fn dedupe_images(app: &AppHandle) -> Result<DedupeReport, String> {
+    // Queued edits land first, so they can't write old references back afterwards
+    if !app.state::<WriteQueue>().flush(FLUSH_TIMEOUT) {
+        return Err("Pending note saves did not finish, try again".to_string());
+    }
+
+    let index = app.state::<ImageIndex>();
+    let mut guard = index.lock();
+
+    let images_dir = get_images_dir(app);
+    let scanned = scan_images(&images_dir);
+    let hashes = index_scan(&scanned);
+    let merged: BTreeMap<String, String> = scanned
+        .iter()
+        .filter(|(name, hash)| hashes.get(*hash) != Some(*name))
+        .map(|(name, hash)| (name.clone(), hashes[hash].clone()))
+        .collect();
+
+    let mut rewritten_notes = Vec::new();
+    let notes_dir = get_notes_dir(app);
+    for path in [notes_dir.clone(), get_trash_dir(app)]
+        .iter()
+        .flat_map(|dir| files_in(dir))
+        .filter(|p| is_markdown(p))
+    {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Some(rewritten) = rewrite_references(&content, &merged) else {
+            continue;
+        };
+        let id = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        // Notes may be open in an editor, so they go through the queue, which tells it to reload
+        if path.parent() == Some(notes_dir.as_path()) {
+            app.state::<WriteQueue>().rewrite(app, &id, rewritten)?;
+        } else {
+            fs::write(&path, rewritten).map_err(|e| e.to_string())?;
+        }
+        rewritten_notes.push(id);
+    }
+
+    // Templates refer to images too, and new notes would otherwise get the trashed copy
+    for path in files_in(&get_templates_dir(app)).into_iter().filter(|p| is_markdown(p)) {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Some(rewritten) = rewrite_references(&content, &merged) {
+            fs::write(&path, rewritten).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut freed_bytes = 0;
+    remove_thumbnails(app, &merged.keys().cloned().collect::<Vec<_>>());
+    for name in merged.keys() {
+        let path = images_dir.join(name);
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        // Nothing refers to it anymore, so a failure only leaves an orphan behind
+        match move_to_trash(app, &path) {
+            Ok(_) => freed_bytes += size,
+            Err(e) => log::warn!("Failed to move duplicate image {} to trash: {}", name, e),
+        }
+    }
+
+    write_index(app, &hashes)?;
+    *guard = Some(hashes);
+
+    if !rewritten_notes.is_empty() {
+        let _ = app.emit("notes-changed", ());
+    }
+    log::info!("Merged {} duplicate images, rewrote {} notes", merged.len(), rewritten_notes.len());
+
+    Ok(DedupeReport { merged, rewritten_notes, freed_bytes })
+}
+
+/// Ids of the notes in `dir` referring to each file `references` finds
+fn usage_in(dir: &Path, references: fn(&str) -> Vec<String>) -> HashMap<String, Vec<String>> {
+    let mut usage: HashMap<String, Vec<String>> = HashMap::new();
+
+    for path in files_in(dir).into_iter().filter(|p| is_markdown(p)) {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let id = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let names: HashSet<String> = references(&content).into_iter().collect();
+        for name in names {
+            usage.entry(name).or_default().push(id.clone());
+        }
+    }
+
+    usage
+}
+
+pub(crate) fn grace_days(app: &AppHandle) -> u32 {
+    app.state::<SettingsStore>().get().retention.orphaned_image_days
+}
+
+/// Every file in `dir` with the notes using it and its size
+fn file_usage(
+    app: &AppHandle,
+    dir: &Path,
+    orphans: &[PathBuf],
+    references: fn(&str) -> Vec<String>,
+) -> Vec<FileUsage> {
+    let orphans: HashSet<String> = orphans.iter().map(|p| file_name(p)).collect();
+    let mut usage = usage_in(&get_notes_dir(app), references);
+    let trashed = usage_in(&get_trash_dir(app), references);
+
+    let mut files: Vec<FileUsage> = files_in(dir)
+        .into_iter()
+        .filter(|path| path.is_file())
+        .map(|path| {
+            let name = file_name(&path);
+            let mut notes = usage.remove(&name).unwrap_or_default();
+            notes.sort();
+            FileUsage {
+                size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                notes,
+                trashed_notes: trashed.get(&name).map_or(0, Vec::len),
+                orphaned: orphans.contains(&name),
+                name,
+            }
+        })
+        .collect();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    files
+}
+
+/// Every stored image and attachment with the notes using it and its size, flagging orphans
+pub fn image_report(app: &AppHandle) -> ImageReport {
+    let grace_days = grace_days(app);
+    let min_age = DAY * grace_days;
+
+    let images = file_usage(app, &get_images_dir(app), &orphaned_images(app, min_age), |content| {
+        image_references(content).map(String::from).collect()
+    });
+    let attachments = file_usage(app, &get_attachments_dir(app), &orphaned_attachments(app, min_age), |content| {
+        attachment_references(content).collect()
+    });
+
+    let all = || images.iter().chain(&attachments);
+    ImageReport {
+        total_size: all().map(|file| file.size).sum(),
+        orphaned_size: all().filter(|file| file.orphaned).map(|file| file.size).sum(),
+        images,
+        attachments,
+        grace_days,
+    }
+}
+
+#[tauri::command]
+pub async fn get_thumbnails_path(app: AppHandle) -> Result<String, String> {
+    Ok(get_thumbnails_dir(&app).to_string_lossy().to_string())
+}
+
+/// Cross-reference every stored image and attachment against the notes
+#[tauri::command]
+pub async fn get_image_report(app: AppHandle) -> Result<ImageReport, String> {
+    Ok(image_report(&app))
+}
+
+/// Move the orphans the user confirmed from a report to the trash. Each one is checked
+/// again, so an image referenced since the report was made is kept.
+#[tauri::command]
+pub async fn trash_orphaned_images(app: AppHandle, names: Vec<String>) -> Result<Vec<String>, String> {
+    let orphans = orphaned_images(&app, DAY * grace_days(&app));
+    let mut moved = Vec::new();
+
+    for path in orphans.iter().filter(|path| names.contains(&file_name(path))) {
+        if let Err(e) = move_to_trash(&app, path) {
+            log::warn!("Failed to move orphaned image {} to trash: {}", path.display(), e);
+            continue;
+        }
+        moved.push(file_name(path));
+    }
+
+    if !moved.is_empty() {
+        app.state::<ImageIndex>().forget(&app, &moved);
+        log::info!("Moved orphaned images to trash: {:?}", moved);
+    }
+    Ok(moved)
+}
+
+/// Merge identical stored images into one file each, rewriting the notes that use them
+#[tauri::command]
+pub async fn dedupe_stored_images(app: AppHandle) -> Result<DedupeReport, String> {
+    dedupe_images(&app)
+}
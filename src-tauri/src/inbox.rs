@@ -0,0 +1,65 @@
+use crate::commands::read_note_meta;
+use crate::periodic;
+use crate::storage::{ensure_notes_dir, NoteMeta};
+use crate::writer::WriteQueue;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::{AppHandle, Manager};
+
+/// ID of the note that collects appended captures
+pub const INBOX_NOTE_ID: &str = "inbox";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AppendTarget {
+    Inbox,
+    Today,
+}
+
+/// Format a capture as a timestamped entry
+fn format_entry(target: AppendTarget, text: &str) -> String {
+    let now = Local::now();
+    let stamp = match target {
+        // Today's note already carries the date in its title
+        AppendTarget::Today => now.format("%H:%M"),
+        AppendTarget::Inbox => now.format("%Y-%m-%d %H:%M"),
+    };
+    format!("### {}\n\n{}\n", stamp, text.trim())
+}
+
+/// Separator needed so a new entry starts after a blank line
+fn separator_for(content: &str) -> &'static str {
+    if content.is_empty() || content.ends_with("\n\n") {
+        ""
+    } else if content.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    }
+}
+
+/// Append `text` to the target note and return the updated note. It goes through the write
+/// queue, so an editor showing the note can't save over the entry and reloads with it.
+pub fn append_entry(app: &AppHandle, target: AppendTarget, text: &str) -> Result<NoteMeta, String> {
+    if text.trim().is_empty() {
+        return Err("Nothing to append".to_string());
+    }
+
+    let id = match target {
+        AppendTarget::Inbox => INBOX_NOTE_ID.to_string(),
+        AppendTarget::Today => periodic::open_today(app)?.note.id,
+    };
+
+    let entry = format_entry(target, text);
+    app.state::<WriteQueue>().update(app, &id, |content| format!("{}{}{}", content, separator_for(content), entry))?;
+
+    let path = ensure_notes_dir(app)?.join(format!("{}.md", id));
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    read_note_meta(&path, &content)
+}
+
+#[tauri::command]
+pub async fn append_to_note(app: AppHandle, target: AppendTarget, text: String) -> Result<NoteMeta, String> {
+    append_entry(&app, target, &text)
+}
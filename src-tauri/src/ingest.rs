@@ -0,0 +1,156 @@
+use crate::settings::ImageSettings;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageFormat, ImageReader, ImageResult};
+use std::io::Cursor;
+
+/// Longest side of a generated thumbnail
+const THUMBNAIL_SIZE: u32 = 320;
+
+/// How far into a file to look for the `<svg` tag
+const SVG_SNIFF_LEN: usize = 1024;
+
+/// A stored image format, as found from the file's leading bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Raster(ImageFormat),
+    Svg,
+}
+
+/// Image bytes ready to store, with the extension matching their real format
+pub struct Processed {
+    pub data: Vec<u8>,
+    pub extension: &'static str,
+}
+
+fn is_svg(data: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&data[..data.len().min(SVG_SNIFF_LEN)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with('<') && head.contains("<svg")
+}
+
+/// Work out the format from magic bytes, whatever the file was called
+pub fn sniff(data: &[u8]) -> Option<Kind> {
+    match image::guess_format(data) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP | ImageFormat::Bmp)) => {
+            Some(Kind::Raster(format))
+        }
+        Ok(_) => None,
+        Err(_) => is_svg(data).then_some(Kind::Svg),
+    }
+}
+
+fn extension(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Gif => "gif",
+        ImageFormat::WebP => "webp",
+        _ => "png",
+    }
+}
+
+/// A decoded image and what the file carried besides the pixels
+struct Decoded {
+    image: DynamicImage,
+    has_metadata: bool,
+    icc_profile: Option<Vec<u8>>,
+}
+
+/// Decode an image with its EXIF rotation applied to the pixels
+fn decode(data: &[u8], format: ImageFormat) -> Result<Decoded, String> {
+    let mut decoder = ImageReader::with_format(Cursor::new(data), format)
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let has_metadata = decoder.exif_metadata().ok().flatten().is_some() || decoder.xmp_metadata().ok().flatten().is_some();
+    let icc_profile = decoder.icc_profile().ok().flatten();
+
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    image.apply_orientation(orientation);
+    Ok(Decoded { image, has_metadata, icc_profile })
+}
+
+/// Write `image` with `encoder`, keeping the colour profile; it describes the pixels, so it
+/// stays even when metadata is stripped
+fn write_with_profile(image: DynamicImage, mut encoder: impl ImageEncoder, icc_profile: Option<Vec<u8>>) -> ImageResult<()> {
+    if let Some(profile) = icc_profile {
+        // An encoder that can't embed it still writes the pixels
+        let _ = encoder.set_icc_profile(profile);
+    }
+    image.write_with_encoder(encoder)
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat, jpeg_quality: u8, icc_profile: Option<Vec<u8>>) -> Result<Processed, String> {
+    let mut data = Vec::new();
+    let result = match format {
+        ImageFormat::Jpeg => write_with_profile(
+            DynamicImage::ImageRgb8(image.to_rgb8()),
+            JpegEncoder::new_with_quality(&mut data, jpeg_quality),
+            icc_profile,
+        ),
+        ImageFormat::WebP => {
+            write_with_profile(DynamicImage::ImageRgba8(image.to_rgba8()), WebPEncoder::new_lossless(&mut data), icc_profile)
+        }
+        // Everything else, e.g. uncompressed BMP, is stored as PNG
+        _ => write_with_profile(DynamicImage::ImageRgba8(image.to_rgba8()), PngEncoder::new(&mut data), icc_profile),
+    };
+    result.map_err(|e| e.to_string())?;
+
+    Ok(Processed { data, extension: extension(format) })
+}
+
+/// Prepare image bytes for storage: check the real format, scale down to the configured
+/// maximum and drop metadata. Images needing neither, or that would only grow, are stored
+/// byte for byte.
+pub fn process(data: &[u8], settings: &ImageSettings) -> Result<Processed, String> {
+    let format = match sniff(data) {
+        Some(Kind::Raster(format)) => format,
+        Some(Kind::Svg) => return Ok(Processed { data: data.to_vec(), extension: "svg" }),
+        None => return Err("Not a supported image; expected PNG, JPEG, GIF, WebP, BMP or SVG".to_string()),
+    };
+
+    // GIFs carry no EXIF, and re-encoding would keep only the first frame of an animation
+    if format == ImageFormat::Gif {
+        return Ok(Processed { data: data.to_vec(), extension: "gif" });
+    }
+
+    let Decoded { mut image, has_metadata, icc_profile } = decode(data, format)?;
+    let too_large = settings.max_dimension.filter(|max| image.width().max(image.height()) > *max);
+    let strip = settings.strip_metadata && has_metadata;
+
+    if too_large.is_none() && !strip && format != ImageFormat::Bmp {
+        return Ok(Processed { data: data.to_vec(), extension: extension(format) });
+    }
+
+    if let Some(max) = too_large {
+        image = image.resize(max, max, FilterType::Lanczos3);
+    }
+    let processed = encode(&image, format, settings.jpeg_quality, icc_profile)?;
+
+    // WebP is re-encoded losslessly, so a scaled-down lossy photo can come out larger.
+    // The original only stands in when it has no metadata that has to go.
+    if processed.data.len() >= data.len() && !strip && format != ImageFormat::Bmp {
+        return Ok(Processed { data: data.to_vec(), extension: extension(format) });
+    }
+    Ok(processed)
+}
+
+/// A small PNG preview of a stored image; `None` for SVGs, which scale on their own
+pub fn thumbnail(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let Some(Kind::Raster(format)) = sniff(data) else {
+        return Ok(None);
+    };
+
+    let Decoded { image, icc_profile, .. } = decode(data, format)?;
+    let image = if image.width().max(image.height()) > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        image
+    };
+
+    encode(&image, ImageFormat::Png, 0, icc_profile).map(|processed| Some(processed.data))
+}
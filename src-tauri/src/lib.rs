@@ -1,50 +1,160 @@
+mod attachments;
+mod capture;
 mod clipper;
 mod commands;
 mod export;
+mod images;
+mod inbox;
+mod ingest;
+mod markdown;
+mod note_state;
+mod periodic;
+mod retention;
+mod rtf;
+mod settings;
+mod shortcuts;
+mod shutdown;
 mod storage;
+mod switcher;
+mod templates;
+mod tray;
+mod window_state;
+mod windows;
+mod writer;
 
+use capture::{open_capture, CaptureMode, CapturePool};
 use clipper::clip_to_markdown;
+use window_state::WindowStates;
+use settings::SettingsStore;
+use shortcuts::ShortcutRegistry;
 use commands::*;
 use tauri::{
-    menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Manager, RunEvent, WebviewUrl, WebviewWindowBuilder, WindowEvent,
+    AppHandle, Emitter, Listener, Manager, RunEvent, WindowEvent,
 };
-use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
-
-fn create_capture_window(app: &AppHandle) {
-    let window_label = format!("capture-{}", std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis());
-
-    let url = WebviewUrl::App("index.html?window=capture".into());
-
-    let builder = WebviewWindowBuilder::new(app, &window_label, url)
-        .title("Quick Capture")
-        .inner_size(500.0, 400.0)
-        .min_inner_size(300.0, 200.0)
-        .center()
-        .resizable(true)
-        .decorations(false)
-        .visible(false);
-
-    // Transparent windows work on Windows and macOS (with macos-private-api feature)
-    #[cfg(any(target_os = "windows", target_os = "macos"))]
-    let builder = builder.transparent(true).shadow(false);
-
-    match builder.build()
-    {
-        Ok(window) => {
-            let _ = window.show();
-            let _ = window.set_focus();
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// Create a note from the current text selection and open it in a capture window
+fn capture_selection(app: &AppHandle) {
+    // Ask for the source before our own window takes focus
+    let source = clipper::frontmost_app_name();
+
+    let Some(text) = clipper::get_selected_text() else {
+        open_capture(app, CaptureMode::NewNote);
+        return;
+    };
+
+    let content = match source {
+        Some(name) => format!("{}\n\nSource: {}\n", text.trim_end(), name),
+        None => text,
+    };
+
+    match write_new_note(app, &content) {
+        Ok(note) => {
+            let _ = app.emit("notes-changed", ());
+            open_capture(app, CaptureMode::Existing { note: note.id });
+        }
+        Err(e) => log::error!("Failed to capture selection: {}", e),
+    }
+}
+
+/// Create a note holding the clipboard image and open it in a capture window
+fn paste_image_as_note(app: &AppHandle) {
+    let link = match clipper::store_clipboard_image(app) {
+        Ok(Some(link)) => link,
+        Ok(None) => {
+            log::info!("Clipboard has no image to paste as a note");
+            return;
         }
         Err(e) => {
-            log::error!("Failed to create capture window: {}", e);
+            log::error!("Failed to read clipboard image: {}", e);
+            return;
+        }
+    };
+
+    match write_new_note(app, &format!("{}\n", link)) {
+        Ok(note) => {
+            let _ = app.emit("notes-changed", ());
+            open_capture(app, CaptureMode::Existing { note: note.id });
+        }
+        Err(e) => log::error!("Failed to create note from clipboard image: {}", e),
+    }
+}
+
+/// Create a note from the clipboard converted to Markdown and open it in a capture window
+fn clip_as_note(app: &AppHandle) {
+    match clipper::clip_note(app) {
+        Ok(note) => {
+            notify(app, "Clipped to a new note", &note.title);
+            open_capture(app, CaptureMode::Existing { note: note.id });
+        }
+        Err(e) => {
+            log::error!("Clip to note failed: {}", e);
+            notify(app, "Clip to note failed", &e);
+        }
+    }
+}
+
+fn open_today_note(app: &AppHandle) {
+    match periodic::open_today(app) {
+        Ok(today) => {
+            if today.created {
+                let _ = app.emit("notes-changed", ());
+            }
+            if let Err(e) = windows::open_note_window(app, &today.note.id, false) {
+                log::error!("Failed to open today's note: {}", e);
+            }
+        }
+        Err(e) => log::error!("Failed to open today's note: {}", e),
+    }
+}
+
+/// Give every template with a `shortcut:` option its own capture shortcut
+fn register_template_shortcuts(app: &AppHandle) {
+    let templates = match templates::load_templates(app) {
+        Ok(templates) => templates,
+        Err(e) => {
+            log::error!("Failed to load templates: {}", e);
+            return;
+        }
+    };
+
+    for template in templates {
+        let Some(binding) = template.options.get("shortcut") else {
+            continue;
+        };
+
+        let shortcut: Shortcut = match binding.parse() {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                log::warn!("Invalid shortcut '{}' in template '{}': {}", binding, template.name, e);
+                continue;
+            }
+        };
+
+        let app_handle = app.clone();
+        let name = template.name.clone();
+        let result = app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                open_capture(&app_handle, CaptureMode::Template { name: name.clone() });
+            }
+        });
+
+        if let Err(e) = result {
+            log::error!("Failed to register shortcut for template '{}': {}", template.name, e);
         }
     }
 }
 
+/// Show a system notification, for shortcuts that work without a visible window
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    use tauri_plugin_notification::NotificationExt;
+
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("Failed to show notification: {}", e);
+    }
+}
+
 fn focus_main_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         let _ = window.show();
@@ -60,8 +170,18 @@ pub fn run() {
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .manage(CapturePool::default())
+        .manage(switcher::SearchIndex::default())
+        .manage(ShortcutRegistry::default())
+        .manage(shutdown::Shutdown::default())
+        .manage(writer::WriteQueue::default())
+        .manage(images::ImageIndex::default())
         .setup(|app| {
+            app.manage(WindowStates::load(app.handle()));
+            windows::restore_session(app.handle());
+
             if cfg!(debug_assertions) {
                 app.handle().plugin(
                     tauri_plugin_log::Builder::default()
@@ -70,56 +190,55 @@ pub fn run() {
                 )?;
             }
 
-            // Register global shortcuts
-            let capture_shortcut = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyN);
-            let focus_shortcut = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyG);
-            let clip_shortcut = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyV);
+            let settings = SettingsStore::load(app.handle());
 
-            let app_handle = app.handle().clone();
-            app.global_shortcut().on_shortcut(capture_shortcut, move |_app, _shortcut, event| {
-                if event.state == ShortcutState::Pressed {
-                    create_capture_window(&app_handle);
-                }
-            })?;
+            // Global shortcuts, re-registered whenever their settings change
+            app.state::<ShortcutRegistry>().register_all(app.handle(), &settings.get().shortcuts);
+            settings.subscribe(|app, old, new| {
+                app.state::<ShortcutRegistry>()
+                    .apply(app, &old.shortcuts, &new.shortcuts)
+                    .map_err(Into::into)
+            });
+            app.manage(settings);
 
-            let app_handle = app.handle().clone();
-            app.global_shortcut().on_shortcut(focus_shortcut, move |_app, _shortcut, event| {
-                if event.state == ShortcutState::Pressed {
-                    focus_main_window(&app_handle);
-                }
-            })?;
-
-            // Web clipper: Ctrl+Alt+V - converts clipboard HTML to Markdown
-            app.global_shortcut().on_shortcut(clip_shortcut, move |_app, _shortcut, event| {
-                if event.state == ShortcutState::Pressed {
-                    match clip_to_markdown() {
-                        Ok(_) => log::info!("Clipboard converted to Markdown"),
-                        Err(e) => log::error!("Clip to markdown failed: {}", e),
-                    }
-                }
-            })?;
+            // Template captures, e.g. "meeting note" or "bug report"
+            register_template_shortcuts(app.handle());
 
-            // System tray
-            let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let new_note = MenuItem::with_id(app, "new_note", "New Note", true, None::<&str>)?;
-            let show = MenuItem::with_id(app, "show", "Show Gravity", true, None::<&str>)?;
+            // Empty notes, trash, orphaned images and stale state, per the retention settings
+            retention::start(app.handle());
+            writer::start(app.handle());
+            images::start(app.handle());
 
-            let menu = Menu::with_items(app, &[&show, &new_note, &quit])?;
+            // Keep a hidden capture window ready for the first capture
+            capture::schedule_prewarm(app.handle());
 
-            let _tray = TrayIconBuilder::new()
+            // System tray, rebuilt whenever notes change
+            let notes = tray::list_notes(app.handle());
+            let menu = tray::build_menu(app.handle(), notes.as_deref().unwrap_or_default())?;
+
+            let _tray = TrayIconBuilder::with_id(tray::TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
+                .tooltip(tray::tooltip(&notes))
                 .menu(&menu)
                 .on_menu_event(move |app, event| match event.id.as_ref() {
                     "quit" => {
                         app.exit(0);
                     }
                     "new_note" => {
-                        let _ = create_capture_window(app);
+                        open_capture(app, CaptureMode::NewNote);
+                    }
+                    "today" => {
+                        open_today_note(app);
+                    }
+                    "search" => {
+                        switcher::open_switcher(app);
                     }
                     "show" => {
                         focus_main_window(app);
                     }
-                    _ => {}
+                    _ => {
+                        tray::handle_menu_event(app, &event);
+                    }
                 })
                 .on_tray_icon_event(|tray, event| {
                     if let TrayIconEvent::Click {
@@ -134,6 +253,11 @@ pub fn run() {
                 })
                 .build(app)?;
 
+            let app_handle = app.handle().clone();
+            app.listen_any("notes-changed", move |_event| {
+                tray::schedule_refresh(&app_handle);
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -142,15 +266,52 @@ pub fn run() {
             delete_note,
             get_note,
             list_notes,
+            set_note_pinned,
+            set_note_starred,
+            set_note_archived,
+            mark_note_opened,
+            reorder_pinned_notes,
             delete_if_empty,
             cleanup_empty_notes,
             save_image,
             get_images_path,
+            images::get_thumbnails_path,
+            images::get_image_report,
+            images::trash_orphaned_images,
+            images::dedupe_stored_images,
+            attachments::save_attachment,
+            attachments::attach_file,
+            attachments::open_attachment,
+            attachments::trash_orphaned_attachments,
+            templates::list_templates,
+            templates::create_note_from_template,
+            templates::get_templates_path,
+            periodic::open_periodic_note,
+            inbox::append_to_note,
+            capture::capture_ready,
+            capture::capture_note_created,
+            shutdown::shutdown_flushed,
+            switcher::search_notes,
+            switcher::create_note_with_text,
+            shortcuts::get_shortcuts,
+            shortcuts::set_shortcut,
+            settings::get_settings,
+            settings::update_settings,
+            settings::export_settings,
+            settings::import_settings,
+            retention::run_retention,
+            writer::queue_note_write,
+            windows::open_note,
+            windows::set_note_window_pinned,
+            windows::set_note_window_opacity,
             export::export_note_file,
             export::export_pdf,
             export::get_downloads_dir,
             export::reveal_in_folder,
             clip_to_markdown,
+            clipper::clip_to_note,
+            clipper::copy_as_rich_text,
+            clipper::paste_clipboard_image,
         ])
         .on_window_event(|window, event| {
             // Hide main window instead of closing it
@@ -160,13 +321,30 @@ pub fn run() {
                     api.prevent_close();
                 }
             }
+
+            windows::handle_window_event(window, event);
+
+            if let WindowEvent::Destroyed = event {
+                if window.label().starts_with("capture-") {
+                    capture::forget_window(window.app_handle(), window.label());
+                }
+                shutdown::forget_window(window.app_handle(), window.label());
+            }
         })
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
-        .run(|_app, event| {
-            // Prevent app from exiting when all windows are closed
-            if let RunEvent::ExitRequested { api, .. } = event {
-                api.prevent_exit();
-            }
+        .run(|app, event| match event {
+            RunEvent::ExitRequested { api, code, .. } => match code {
+                // Prevent app from exiting when all windows are closed
+                None => api.prevent_exit(),
+                // Explicit quit: let the windows flush and clean up first, then exit again
+                Some(code) if !app.state::<shutdown::Shutdown>().is_done() => {
+                    api.prevent_exit();
+                    shutdown::request(app, code);
+                }
+                Some(_) => {}
+            },
+            RunEvent::Exit => shutdown::on_exit(app),
+            _ => {}
         });
 }
@@ -0,0 +1,798 @@
+use crate::retention::ATTACHMENT_URL_PREFIX;
+use html5ever::tendril::TendrilSink;
+use html5ever::{parse_document, ParseOpts};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use pulldown_cmark::html::push_html;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Elements whose content never belongs in a note
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "template", "head", "title", "meta", "link", "iframe", "object", "embed", "svg",
+    "canvas", "button", "select", "textarea",
+];
+
+/// Elements that start a new block; everything else is rendered inline
+const BLOCKS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "center", "dd", "details", "dialog", "div", "dl", "dt",
+    "fieldset", "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hgroup",
+    "hr", "html", "li", "main", "nav", "ol", "p", "pre", "section", "summary", "table", "ul",
+];
+
+/// Prefixes sites put before footnote numbers in element IDs, e.g. `fn1` or `cite_note-3`
+const FOOTNOTE_ID_PREFIXES: &[&str] = &["cite_note-", "footnote-", "footnote", "fn:", "fn-", "fn", "note-"];
+
+/// A rendered block. Lists are marked so they can sit directly under the text of a list item.
+struct Block {
+    text: String,
+    list: bool,
+}
+
+#[derive(Default)]
+struct Converter {
+    /// Footnote definitions in document order, as (label, text)
+    footnotes: Vec<(String, String)>,
+}
+
+/// Whether a custom element, e.g. GitHub's `<markdown-accessiblity-table>`, wraps block content
+fn wraps_blocks(node: &Handle) -> bool {
+    tag(node).is_some_and(|name| name.contains('-'))
+        && node.children.borrow().iter().any(|child| {
+            tag(child).is_some_and(|name| BLOCKS.contains(&name)) || wraps_blocks(child)
+        })
+}
+
+fn tag(node: &Handle) -> Option<&str> {
+    match &node.data {
+        NodeData::Element { name, .. } => Some(name.local.as_ref()),
+        _ => None,
+    }
+}
+
+fn attr(node: &Handle, name: &str) -> Option<String> {
+    match &node.data {
+        NodeData::Element { attrs, .. } => attrs
+            .borrow()
+            .iter()
+            .find(|attribute| attribute.name.local.as_ref() == name)
+            .map(|attribute| attribute.value.to_string()),
+        _ => None,
+    }
+}
+
+fn classes(node: &Handle) -> Vec<String> {
+    attr(node, "class")
+        .map(|class| class.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn has_class(node: &Handle, name: &str) -> bool {
+    classes(node).iter().any(|class| class == name)
+}
+
+/// Element children, skipping text and comments
+fn elements(node: &Handle) -> Vec<Handle> {
+    node.children.borrow().iter().filter(|child| tag(child).is_some()).cloned().collect()
+}
+
+/// Collapse runs of whitespace to single spaces, as a browser would
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space {
+            collapsed.push(' ');
+            space = false;
+        }
+        collapsed.push(c);
+    }
+    if space {
+        collapsed.push(' ');
+    }
+    collapsed
+}
+
+/// Escape characters that would otherwise start emphasis, code or links.
+/// Underscores inside words can't start emphasis and are left alone.
+pub(crate) fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut escaped = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let within_word = |offset: isize| {
+            let index = i as isize + offset;
+            index >= 0 && chars.get(index as usize).is_some_and(|c| c.is_alphanumeric())
+        };
+        let needs_escape = match c {
+            '\\' | '*' | '`' | '[' | ']' => true,
+            '_' => !(within_word(-1) && within_word(1)),
+            '<' => chars.get(i + 1).is_some_and(|next| next.is_ascii_alphabetic() || matches!(next, '/' | '!')),
+            _ => false,
+        };
+        if needs_escape {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape a line that would otherwise read as a heading, quote, list item or rule
+pub(crate) fn escape_line_start(line: &str) -> String {
+    let mut chars = line.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c @ ('#' | '>' | '+' | '-' | '=')), next) if next.is_none() || next == Some(' ') || next == Some(c) => {
+            return format!("\\{}", line);
+        }
+        _ => {}
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ") || rest == "." || rest == ")") {
+        return format!("{}\\{}", &line[..digits], rest);
+    }
+    line.to_string()
+}
+
+/// Append collapsed text, dropping a space that would double one already there
+fn push_text(out: &mut String, text: &str) {
+    let text = match text.strip_prefix(' ') {
+        Some(rest) if out.is_empty() || out.ends_with([' ', '\n']) => rest,
+        _ => text,
+    };
+    out.push_str(text);
+}
+
+/// Turn the inline buffer into a paragraph. Line breaks from `<br>` become hard breaks.
+fn paragraph(inline: &str) -> String {
+    let lines: Vec<String> = inline
+        .trim()
+        .lines()
+        .map(|line| escape_line_start(line.trim()))
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines.join("\\\n")
+}
+
+fn flush(inline: &mut String, blocks: &mut Vec<Block>) {
+    let text = paragraph(inline);
+    if !text.is_empty() {
+        blocks.push(Block { text, list: false });
+    }
+    inline.clear();
+}
+
+/// Join blocks with blank lines; inside list items, nested lists follow the text directly
+fn join(blocks: &[Block], tight: bool) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        if !out.is_empty() {
+            out.push_str(if tight && block.list { "\n" } else { "\n\n" });
+        }
+        out.push_str(&block.text);
+    }
+    out
+}
+
+/// Indent every line after the first, leaving blank lines empty
+fn indent(text: &str, prefix: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            out.push('\n');
+            if !line.is_empty() {
+                out.push_str(prefix);
+            }
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Wrap inline content in a delimiter such as `**`, keeping outer spaces outside it
+fn wrap(out: &mut String, inner: &str, delimiter: &str) {
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        push_text(out, if inner.is_empty() { "" } else { " " });
+        return;
+    }
+    if inner.starts_with(' ') {
+        push_text(out, " ");
+    }
+    out.push_str(delimiter);
+    out.push_str(trimmed);
+    out.push_str(delimiter);
+    if inner.ends_with(' ') {
+        out.push(' ');
+    }
+}
+
+/// A backtick code span long enough to hold any backticks in `code`
+fn code_span(code: &str) -> String {
+    let longest = longest_run(code, '`');
+    let fence = "`".repeat(longest + 1);
+    let padding = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+    format!("{}{}{}{}{}", fence, padding, code, padding, fence)
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c).map(str::len).max().unwrap_or(0)
+}
+
+/// Link destination with the characters that would end it early percent-encoded
+fn destination(url: &str) -> String {
+    url.trim().replace(' ', "%20").replace('(', "%28").replace(')', "%29")
+}
+
+fn link_title(node: &Handle) -> String {
+    attr(node, "title")
+        .filter(|title| !title.trim().is_empty())
+        .map(|title| format!(" \"{}\"", title.trim().replace('"', "\\\"")))
+        .unwrap_or_default()
+}
+
+/// Footnote label from the ID a reference points at, e.g. `fn:2` becomes `2`
+fn footnote_label(id: &str) -> String {
+    let id = id.strip_prefix("user-content-").unwrap_or(id);
+    let label = FOOTNOTE_ID_PREFIXES
+        .iter()
+        .find_map(|prefix| id.strip_prefix(prefix))
+        .filter(|label| !label.is_empty())
+        .unwrap_or(id);
+    label.replace(char::is_whitespace, "-")
+}
+
+/// The target of an in-page link, when it points at a footnote
+fn footnote_reference(node: &Handle) -> Option<String> {
+    let href = attr(node, "href")?;
+    let target = href.strip_prefix('#').filter(|target| !target.is_empty())?;
+    Some(footnote_label(target))
+}
+
+/// Links from a footnote back to where it was referenced
+fn is_footnote_backlink(node: &Handle) -> bool {
+    let href = attr(node, "href").unwrap_or_default();
+    attr(node, "role").as_deref() == Some("doc-backlink")
+        || attr(node, "data-footnote-backref").is_some()
+        || classes(node).iter().any(|class| class.contains("footnote-back") || class == "reversefootnote")
+        || href.starts_with("#fnref")
+        || href.starts_with("#user-content-fnref")
+}
+
+fn is_footnote_section(node: &Handle) -> bool {
+    matches!(attr(node, "role").as_deref(), Some("doc-endnotes" | "doc-footnotes"))
+        || has_class(node, "footnotes")
+        || (tag(node) == Some("ol") && has_class(node, "references"))
+}
+
+/// Code language from classes like `language-rust`, `lang-rust` or GitHub's `highlight-source-rust`
+fn code_language(nodes: &[&Handle]) -> Option<String> {
+    nodes.iter().find_map(|node| {
+        let from_class = classes(node).into_iter().find_map(|class| {
+            ["language-", "lang-", "highlight-source-"]
+                .iter()
+                .find_map(|prefix| class.strip_prefix(prefix).map(str::to_string))
+        });
+        from_class
+            .or_else(|| attr(node, "data-lang"))
+            .or_else(|| attr(node, "data-language"))
+            .filter(|language| !language.is_empty() && !language.contains(char::is_whitespace))
+    })
+}
+
+/// Text of a `<pre>` exactly as written, with `<br>` as a line break
+fn raw_text(node: &Handle, out: &mut String) {
+    for child in node.children.borrow().iter() {
+        match &child.data {
+            NodeData::Text { contents } => out.push_str(&contents.borrow()),
+            NodeData::Element { .. } => match tag(child) {
+                Some("br") => out.push('\n'),
+                Some(name) if SKIPPED.contains(&name) => {}
+                _ => raw_text(child, out),
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Alignment row entry for a header cell's `align` attribute or `text-align` style
+fn alignment(cell: &Handle) -> &'static str {
+    let style = attr(cell, "style").unwrap_or_default().replace(' ', "").to_ascii_lowercase();
+    let align = attr(cell, "align").unwrap_or_default().to_ascii_lowercase();
+    if align == "center" || style.contains("text-align:center") {
+        ":---:"
+    } else if align == "right" || style.contains("text-align:right") {
+        "---:"
+    } else if align == "left" || style.contains("text-align:left") {
+        ":---"
+    } else {
+        "---"
+    }
+}
+
+/// Table rows in order, looking through `thead`, `tbody` and `tfoot` but not into nested tables
+fn table_rows(table: &Handle) -> Vec<Handle> {
+    let mut rows = Vec::new();
+    for child in elements(table) {
+        match tag(&child) {
+            Some("tr") => rows.push(child),
+            Some("thead" | "tbody" | "tfoot") => {
+                rows.extend(elements(&child).into_iter().filter(|row| tag(row) == Some("tr")));
+            }
+            _ => {}
+        }
+    }
+    rows
+}
+
+/// Footnote list items with their IDs, in document order
+fn footnote_items(node: &Handle, items: &mut Vec<(String, Handle)>) {
+    for child in elements(node) {
+        match (tag(&child), attr(&child, "id")) {
+            (Some("li"), Some(id)) => items.push((id, child)),
+            _ => footnote_items(&child, items),
+        }
+    }
+}
+
+impl Converter {
+    /// Render a node's children as blocks, gathering loose inline content into paragraphs
+    fn blocks(&mut self, node: &Handle) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut inline = String::new();
+
+        for child in node.children.borrow().iter() {
+            match tag(child) {
+                Some(name) if BLOCKS.contains(&name) || wraps_blocks(child) => {
+                    flush(&mut inline, &mut blocks);
+                    blocks.extend(self.block(child, name, node));
+                }
+                _ => self.inline(child, &mut inline),
+            }
+        }
+        flush(&mut inline, &mut blocks);
+        blocks
+    }
+
+    fn block(&mut self, node: &Handle, name: &str, parent: &Handle) -> Vec<Block> {
+        let text = match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let text = self.inline_text(node).replace('\n', " ");
+                if text.is_empty() {
+                    return Vec::new();
+                }
+                format!("{} {}", "#".repeat(level), text)
+            }
+            "hr" => "---".to_string(),
+            "pre" => self.code_block(node, parent),
+            "blockquote" => {
+                let inner = join(&self.blocks(node), false);
+                inner
+                    .lines()
+                    .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            "ul" | "ol" if !is_footnote_section(node) => return self.list(node, name == "ol"),
+            "table" => return self.table(node),
+            _ if is_footnote_section(node) && self.collect_footnotes(node) => return Vec::new(),
+            _ => return self.blocks(node),
+        };
+
+        if text.trim().is_empty() {
+            Vec::new()
+        } else {
+            vec![Block { text, list: false }]
+        }
+    }
+
+    /// Inline content of a node as one trimmed line per `<br>`
+    fn inline_text(&mut self, node: &Handle) -> String {
+        let mut out = String::new();
+        self.inline_children(node, &mut out);
+        out.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n")
+    }
+
+    fn inline_children(&mut self, node: &Handle, out: &mut String) {
+        for child in node.children.borrow().iter() {
+            self.inline(child, out);
+        }
+    }
+
+    fn inline(&mut self, node: &Handle, out: &mut String) {
+        let name = match &node.data {
+            NodeData::Text { contents } => {
+                let text = collapse_whitespace(&contents.borrow().replace('\u{a0}', " "));
+                push_text(out, &escape(&text));
+                return;
+            }
+            NodeData::Element { name, .. } => name.local.as_ref(),
+            _ => return,
+        };
+
+        match name {
+            _ if SKIPPED.contains(&name) => {}
+            "br" => {
+                let trimmed = out.trim_end_matches(' ').len();
+                out.truncate(trimmed);
+                out.push('\n');
+            }
+            "strong" | "b" => {
+                let mut inner = String::new();
+                self.inline_children(node, &mut inner);
+                wrap(out, &inner, "**");
+            }
+            "em" | "i" | "cite" | "dfn" => {
+                let mut inner = String::new();
+                self.inline_children(node, &mut inner);
+                wrap(out, &inner, "*");
+            }
+            "del" | "s" | "strike" => {
+                let mut inner = String::new();
+                self.inline_children(node, &mut inner);
+                wrap(out, &inner, "~~");
+            }
+            "code" | "kbd" | "samp" | "tt" | "pre" => {
+                let mut code = String::new();
+                raw_text(node, &mut code);
+                let code = collapse_whitespace(&code);
+                let code = code.trim();
+                if !code.is_empty() {
+                    out.push_str(&code_span(code));
+                }
+            }
+            "a" => self.link(node, out),
+            "img" => self.image(node, out),
+            "sup" => {
+                let children = elements(node);
+                let reference = match children.as_slice() {
+                    [only] if tag(only) == Some("a") => footnote_reference(only),
+                    _ => None,
+                };
+                match reference {
+                    Some(label) => out.push_str(&format!("[^{}]", label)),
+                    None => self.inline_children(node, out),
+                }
+            }
+            "input" => {
+                // Task list checkboxes only mean something at the start of an item
+                let checkbox = attr(node, "type").is_some_and(|kind| kind.eq_ignore_ascii_case("checkbox"));
+                if checkbox && out.trim().is_empty() {
+                    out.clear();
+                    out.push_str(if attr(node, "checked").is_some() { "[x] " } else { "[ ] " });
+                }
+            }
+            "span" if has_class(node, "mw-cite-backlink") => {}
+            _ if BLOCKS.contains(&name) => {
+                // Blocks inside inline content, e.g. a `<div>` in a link, become spaced runs of text
+                push_text(out, " ");
+                self.inline_children(node, out);
+                push_text(out, " ");
+            }
+            _ => self.inline_children(node, out),
+        }
+    }
+
+    fn link(&mut self, node: &Handle, out: &mut String) {
+        if is_footnote_backlink(node) {
+            return;
+        }
+        let is_reference = attr(node, "role").as_deref() == Some("doc-noteref")
+            || attr(node, "data-footnote-ref").is_some()
+            || has_class(node, "footnote-ref");
+        if let Some(label) = footnote_reference(node).filter(|_| is_reference) {
+            out.push_str(&format!("[^{}]", label));
+            return;
+        }
+
+        let mut inner = String::new();
+        self.inline_children(node, &mut inner);
+        let text = inner.replace('\n', " ");
+        let text = text.trim();
+
+        let href = attr(node, "href").unwrap_or_default();
+        let href = href.trim();
+        // In-page anchors and scripts lead nowhere from a note
+        if href.is_empty() || href.starts_with('#') || href.to_ascii_lowercase().starts_with("javascript:") {
+            push_text(out, &inner);
+            return;
+        }
+        if text.is_empty() {
+            return;
+        }
+
+        if inner.starts_with(' ') {
+            push_text(out, " ");
+        }
+        if text == href && (href.starts_with("http://") || href.starts_with("https://")) {
+            out.push_str(&format!("<{}>", href));
+        } else {
+            out.push_str(&format!("[{}]({}{})", text, destination(href), link_title(node)));
+        }
+        if inner.ends_with(' ') {
+            out.push(' ');
+        }
+    }
+
+    fn image(&mut self, node: &Handle, out: &mut String) {
+        let src = attr(node, "src")
+            .filter(|src| !src.trim().is_empty())
+            .or_else(|| attr(node, "data-src"));
+        let Some(src) = src else {
+            return;
+        };
+        let alt = collapse_whitespace(&attr(node, "alt").unwrap_or_default());
+        let alt = alt.trim().replace('[', "\\[").replace(']', "\\]");
+        out.push_str(&format!("![{}]({}{})", alt, destination(&src), link_title(node)));
+    }
+
+    fn code_block(&mut self, node: &Handle, parent: &Handle) -> String {
+        let mut code = String::new();
+        raw_text(node, &mut code);
+        let code = code.trim_end_matches(['\n', '\r', ' ', '\t']).trim_start_matches(['\n', '\r']);
+        if code.trim().is_empty() {
+            return String::new();
+        }
+
+        let inner = elements(node).into_iter().find(|child| tag(child) == Some("code"));
+        let mut candidates = vec![node, parent];
+        if let Some(inner) = &inner {
+            candidates.insert(0, inner);
+        }
+        let language = code_language(&candidates).unwrap_or_default();
+
+        let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+        format!("{}{}\n{}\n{}", fence, language, code, fence)
+    }
+
+    fn list(&mut self, node: &Handle, ordered: bool) -> Vec<Block> {
+        let mut number = attr(node, "start").and_then(|start| start.trim().parse::<u64>().ok()).unwrap_or(1);
+        let mut items: Vec<String> = Vec::new();
+
+        for child in elements(node) {
+            match tag(&child) {
+                Some("li") => {
+                    let marker = if ordered { format!("{}. ", number) } else { "- ".to_string() };
+                    number += 1;
+                    let content = join(&self.blocks(&child), true);
+                    let content = indent(&content, &" ".repeat(marker.len()));
+                    items.push(format!("{}{}", marker, content).trim_end().to_string());
+                }
+                // A list directly inside a list belongs to the item before it
+                Some(name @ ("ul" | "ol")) => {
+                    let nested = join(&self.list(&child, name == "ol"), false);
+                    match items.last_mut() {
+                        Some(last) => {
+                            let width = if ordered { format!("{}. ", number - 1).len() } else { 2 };
+                            let prefix = " ".repeat(width);
+                            last.push('\n');
+                            last.push_str(&prefix);
+                            last.push_str(&indent(&nested, &prefix));
+                        }
+                        None => items.push(nested),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if items.is_empty() {
+            return Vec::new();
+        }
+        vec![Block { text: items.join("\n"), list: true }]
+    }
+
+    /// Render a table as a GFM table; single-column layout tables become plain blocks
+    fn table(&mut self, node: &Handle) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        if let Some(caption) = elements(node).into_iter().find(|child| tag(child) == Some("caption")) {
+            blocks.extend(self.blocks(&caption));
+        }
+
+        let rows = table_rows(node);
+        let cells: Vec<Vec<Handle>> = rows
+            .iter()
+            .map(|row| elements(row).into_iter().filter(|cell| matches!(tag(cell), Some("td" | "th"))).collect())
+            .collect();
+        let span = |cell: &Handle| attr(cell, "colspan").and_then(|span| span.trim().parse::<usize>().ok()).unwrap_or(1).clamp(1, 100);
+        let columns = cells.iter().map(|row| row.iter().map(span).sum::<usize>()).max().unwrap_or(0);
+
+        if columns <= 1 {
+            for cell in cells.iter().flatten() {
+                blocks.extend(self.blocks(cell));
+            }
+            return blocks;
+        }
+
+        let mut lines = Vec::new();
+        for (index, row) in cells.iter().enumerate() {
+            let mut texts = Vec::new();
+            let mut aligns = Vec::new();
+            for cell in row {
+                texts.push(self.table_cell(cell));
+                aligns.push(alignment(cell));
+                for _ in 1..span(cell) {
+                    texts.push(String::new());
+                    aligns.push("---");
+                }
+            }
+            texts.resize(columns, String::new());
+            aligns.resize(columns, "---");
+
+            lines.push(format!("| {} |", texts.join(" | ")));
+            if index == 0 {
+                lines.push(format!("| {} |", aligns.join(" | ")));
+            }
+        }
+
+        blocks.push(Block { text: lines.join("\n"), list: false });
+        blocks
+    }
+
+    /// A cell's content on one line, with `<br>` between its lines and pipes escaped
+    fn table_cell(&mut self, cell: &Handle) -> String {
+        let content = join(&self.blocks(cell), false);
+        content
+            .lines()
+            .map(|line| line.strip_suffix('\\').unwrap_or(line).trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("<br>")
+            .replace('|', "\\|")
+    }
+
+    /// Gather the definitions from a footnotes section; false when it holds none
+    fn collect_footnotes(&mut self, node: &Handle) -> bool {
+        let mut items = Vec::new();
+        footnote_items(node, &mut items);
+
+        if items.is_empty() {
+            return false;
+        }
+        for (id, item) in items {
+            let text = join(&self.blocks(&item), false);
+            if !text.is_empty() {
+                self.footnotes.push((footnote_label(&id), text));
+            }
+        }
+        true
+    }
+}
+
+/// Convert an HTML document or fragment to GitHub-flavored Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let dom = parse_document(RcDom::default(), ParseOpts::default()).one(html);
+    let mut converter = Converter::default();
+    let mut blocks = converter.blocks(&dom.document);
+
+    for (label, text) in std::mem::take(&mut converter.footnotes) {
+        blocks.push(Block {
+            text: format!("[^{}]: {}", label, indent(&text, "    ")),
+            list: false,
+        });
+    }
+    join(&blocks, false)
+}
+
+/// Clean up the converted markdown: collapse runs of blank lines and drop trailing
+/// whitespace, leaving indentation and fenced code untouched
+pub fn clean_markdown(md: &str) -> String {
+    let mut result = String::with_capacity(md.len());
+    let mut fence: Option<String> = None;
+    let mut blank = false;
+
+    for line in md.lines() {
+        let trimmed = line.trim_start();
+        let marker: String = trimmed.chars().take_while(|c| *c == '`' || *c == '~').collect();
+
+        match &fence {
+            Some(open) => {
+                if marker.len() >= open.len() && marker.starts_with(&open[..1]) && trimmed[marker.len()..].trim().is_empty() {
+                    fence = None;
+                }
+                result.push_str(line);
+                result.push('\n');
+                continue;
+            }
+            None if marker.len() >= 3 && (marker.chars().all(|c| c == '`') || marker.chars().all(|c| c == '~')) => {
+                fence = Some(marker);
+            }
+            None => {}
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            if blank || result.is_empty() {
+                continue;
+            }
+            blank = true;
+        } else {
+            blank = false;
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    result.trim_end().to_string()
+}
+
+/// Render a note's Markdown to HTML for pasting into other apps. `image_source` gives the
+/// URL to use for each image, e.g. a data URI for a stored one; images it has none for are
+/// replaced by their alt text. Attachment links only work inside Gravity and keep just their text.
+pub fn markdown_to_html(markdown: &str, mut image_source: impl FnMut(&str) -> Option<String>) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_FOOTNOTES;
+    // Whether each open image or link was kept, so its end tag is kept or dropped to match
+    let mut kept = Vec::new();
+
+    let events = Parser::new_ext(markdown, options).filter_map(|event| match event {
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            let source = image_source(&dest_url);
+            kept.push(source.is_some());
+            source.map(|url| Event::Start(Tag::Image { link_type, dest_url: url.into(), title, id }))
+        }
+        Event::Start(Tag::Link { ref dest_url, .. }) => {
+            let keep = !dest_url.starts_with(ATTACHMENT_URL_PREFIX);
+            kept.push(keep);
+            keep.then_some(event)
+        }
+        Event::End(TagEnd::Image | TagEnd::Link) => kept.pop().unwrap_or(true).then_some(event),
+        event => Some(event),
+    });
+
+    let mut html = String::new();
+    push_html(&mut html, events);
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    /// Each `tests/fixtures/clip/*.html` must convert to the Markdown in the `.md` beside it
+    #[test]
+    fn clip_fixtures() {
+        let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/clip"));
+        let mut checked = 0;
+        let mut failures = Vec::new();
+
+        for entry in fs::read_dir(dir).expect("fixture dir") {
+            let path = entry.expect("fixture entry").path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("html") {
+                continue;
+            }
+            let html = fs::read_to_string(&path).expect("read fixture");
+            let expected = fs::read_to_string(path.with_extension("md")).expect("expected markdown");
+            let actual = clean_markdown(&html_to_markdown(&html));
+            if actual != expected.trim_end() {
+                failures.push(format!("{}:\n--- expected\n{}\n--- actual\n{}", path.display(), expected.trim_end(), actual));
+            }
+            checked += 1;
+        }
+
+        assert!(checked > 0, "no fixtures in {}", dir.display());
+        assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+    }
+
+    #[test]
+    fn clean_markdown_keeps_list_indentation() {
+        let md = "+ one\n    + nested  \n1. first\n   continued\n\t- tabbed";
+        assert_eq!(clean_markdown(md), "+ one\n    + nested\n1. first\n   continued\n\t- tabbed");
+    }
+
+    #[test]
+    fn clean_markdown_collapses_blank_lines() {
+        assert_eq!(clean_markdown("\n\n# Title\n\n\n \nText\n\n\n"), "# Title\n\nText");
+    }
+
+    #[test]
+    fn clean_markdown_leaves_fenced_code() {
+        let md = "```\nlet a = 1;  \n\n\n\nlet b = 2;\n```\n\n\n~~~~\n```\nstill code  \n~~~~";
+        assert_eq!(clean_markdown(md), "```\nlet a = 1;  \n\n\n\nlet b = 2;\n```\n\n~~~~\n```\nstill code  \n~~~~");
+    }
+}
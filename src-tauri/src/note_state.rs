@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Sidecar file kept inside the notes directory so it travels with the notes
+const STATE_FILENAME: &str = ".gravity-state.json";
+
+/// Serializes read-modify-write cycles on the sidecar file
+static STATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Persistent per-note flags that live outside the note content
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct NoteState {
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_order: Option<u32>,
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default)]
+    pub archived: bool,
+    /// When the note was last opened in the app, RFC 3339
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_opened: Option<String>,
+    /// Content hash used to find the note again after it was renamed outside the app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+impl NoteState {
+    /// A state with no flags set carries no information and is not stored
+    fn is_default(&self) -> bool {
+        !self.pinned && !self.starred && !self.archived && self.last_opened.is_none()
+    }
+}
+
+pub type NoteStates = HashMap<String, NoteState>;
+
+fn state_path(notes_dir: &Path) -> PathBuf {
+    notes_dir.join(STATE_FILENAME)
+}
+
+fn read_states(notes_dir: &Path) -> NoteStates {
+    fs::read_to_string(state_path(notes_dir))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_states(notes_dir: &Path, states: &NoteStates) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(states).map_err(|e| e.to_string())?;
+    // Write to a temp file first so a crash never leaves a truncated sidecar
+    let tmp = notes_dir.join(format!("{}.tmp", STATE_FILENAME));
+    fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, state_path(notes_dir)).map_err(|e| e.to_string())
+}
+
+/// Stable FNV-1a hash of the note content, hex encoded
+pub fn fingerprint(content: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in content.trim().as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+pub fn load_states(notes_dir: &Path) -> NoteStates {
+    let _guard = STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    read_states(notes_dir)
+}
+
+pub fn get_state(notes_dir: &Path, id: &str) -> NoteState {
+    load_states(notes_dir).remove(id).unwrap_or_default()
+}
+
+/// Apply `change` to the state of one note and persist the result
+pub fn update_state<F>(notes_dir: &Path, id: &str, content: &str, change: F) -> Result<NoteState, String>
+where
+    F: FnOnce(&mut NoteState, &NoteStates),
+{
+    let _guard = STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut states = read_states(notes_dir);
+
+    let mut state = states.get(id).cloned().unwrap_or_default();
+    change(&mut state, &states);
+    state.fingerprint = Some(fingerprint(content));
+
+    if state.is_default() {
+        states.remove(id);
+    } else {
+        states.insert(id.to_string(), state.clone());
+    }
+
+    write_states(notes_dir, &states)?;
+    Ok(state)
+}
+
+/// Record that a note was just opened
+pub fn mark_opened(notes_dir: &Path, id: &str, content: &str) -> Result<NoteState, String> {
+    update_state(notes_dir, id, content, |state, _| {
+        state.last_opened = Some(chrono::Local::now().to_rfc3339());
+    })
+}
+
+/// Forget the state of a deleted note
+pub fn remove_state(notes_dir: &Path, id: &str) -> Result<(), String> {
+    let _guard = STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut states = read_states(notes_dir);
+
+    if states.remove(id).is_some() {
+        write_states(notes_dir, &states)?;
+    }
+    Ok(())
+}
+
+/// Drop states whose note is not in `present`; returns the IDs dropped.
+/// Run `reconcile` first so renamed notes keep their state.
+pub fn compact(notes_dir: &Path, present: &HashSet<String>, dry_run: bool) -> Result<Vec<String>, String> {
+    let _guard = STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut states = read_states(notes_dir);
+
+    let mut stale: Vec<String> = states.keys().filter(|id| !present.contains(*id)).cloned().collect();
+    stale.sort();
+
+    if !dry_run && !stale.is_empty() {
+        states.retain(|id, _| present.contains(id));
+        write_states(notes_dir, &states)?;
+    }
+    Ok(stale)
+}
+
+/// Next free slot at the end of the pinned list
+pub fn next_pin_order(states: &NoteStates) -> u32 {
+    states
+        .values()
+        .filter_map(|s| s.pin_order)
+        .max()
+        .map_or(0, |max| max + 1)
+}
+
+/// Assign pin order from the given list of IDs, in order
+pub fn reorder_pinned(notes_dir: &Path, ids: &[String]) -> Result<(), String> {
+    let _guard = STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut states = read_states(notes_dir);
+
+    for (index, id) in ids.iter().enumerate() {
+        if let Some(state) = states.get_mut(id) {
+            if state.pinned {
+                state.pin_order = Some(index as u32);
+            }
+        }
+    }
+
+    write_states(notes_dir, &states)
+}
+
+/// Re-key states whose note disappeared to a new note with identical content (a rename),
+/// refresh fingerprints of notes edited outside the app, and return the reconciled map.
+///
+/// `notes` is every note currently on disk as `(id, content)`.
+pub fn reconcile(notes_dir: &Path, notes: &[(String, String)]) -> NoteStates {
+    let _guard = STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut states = read_states(notes_dir);
+    let mut changed = false;
+
+    let present: HashSet<&str> = notes.iter().map(|(id, _)| id.as_str()).collect();
+    let missing: Vec<String> = states
+        .keys()
+        .filter(|id| !present.contains(id.as_str()))
+        .cloned()
+        .collect();
+
+    if !missing.is_empty() {
+        let mut unclaimed: HashMap<String, &str> = notes
+            .iter()
+            .filter(|(id, _)| !states.contains_key(id))
+            .map(|(id, content)| (fingerprint(content), id.as_str()))
+            .collect();
+
+        for old_id in missing {
+            let new_id = states[&old_id]
+                .fingerprint
+                .as_ref()
+                .and_then(|fp| unclaimed.remove(fp));
+
+            if let Some(new_id) = new_id {
+                if let Some(state) = states.remove(&old_id) {
+                    log::info!("Note state followed rename {} -> {}", old_id, new_id);
+                    states.insert(new_id.to_string(), state);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    for (id, content) in notes {
+        if let Some(state) = states.get_mut(id) {
+            let current = fingerprint(content);
+            if state.fingerprint.as_deref() != Some(current.as_str()) {
+                state.fingerprint = Some(current);
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        if let Err(e) = write_states(notes_dir, &states) {
+            log::error!("Failed to write note state: {}", e);
+        }
+    }
+
+    states
+}
@@ -0,0 +1,144 @@
+use crate::commands::read_note_meta;
+use crate::settings::{PeriodicSettings, SettingsStore};
+use crate::storage::{ensure_notes_dir, NoteMeta};
+use crate::templates;
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tauri::{AppHandle, Manager};
+
+/// Makes the temp file of each creation attempt unique
+static ATTEMPT: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PeriodKind {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl PeriodKind {
+    /// Name of the template used when the note doesn't exist yet
+    pub fn template_name(self, settings: &PeriodicSettings) -> Option<&str> {
+        match self {
+            PeriodKind::Daily => settings.daily_template.as_deref(),
+            PeriodKind::Weekly => settings.weekly_template.as_deref(),
+            PeriodKind::Monthly => settings.monthly_template.as_deref(),
+        }
+    }
+
+    /// First day of the period containing `date`
+    pub fn period_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            PeriodKind::Daily => date,
+            PeriodKind::Weekly => date
+                .checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))
+                .unwrap_or(date),
+            PeriodKind::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// Deterministic note ID, so there is exactly one note per period
+    pub fn note_id(self, date: NaiveDate) -> String {
+        match self {
+            PeriodKind::Daily => format!("daily-{}", date.format("%Y-%m-%d")),
+            PeriodKind::Weekly => {
+                let week = date.iso_week();
+                format!("weekly-{}-W{:02}", week.year(), week.week())
+            }
+            PeriodKind::Monthly => format!("monthly-{}", date.format("%Y-%m")),
+        }
+    }
+
+    /// Content used when no template is configured for this kind
+    fn default_content(self, date: NaiveDate) -> String {
+        match self {
+            PeriodKind::Daily => format!("# {}\n\n", date.format("%A, %B %-d, %Y")),
+            PeriodKind::Weekly => {
+                let week = date.iso_week();
+                format!("# Week {}, {}\n\n", week.week(), week.year())
+            }
+            PeriodKind::Monthly => format!("# {}\n\n", date.format("%B %Y")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PeriodicNote {
+    pub note: NoteMeta,
+    /// True when this call created the note
+    pub created: bool,
+}
+
+fn parse_date(date: Option<&str>) -> Result<NaiveDate, String> {
+    match date {
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date '{}': {}", value, e)),
+        None => Ok(Local::now().date_naive()),
+    }
+}
+
+/// `date` at the current time of day, used to expand template variables
+fn local_time_on(date: NaiveDate) -> DateTime<Local> {
+    let now = Local::now();
+    date.and_time(now.time())
+        .and_local_timezone(Local)
+        .earliest()
+        .unwrap_or(now)
+}
+
+fn render(app: &AppHandle, kind: PeriodKind, start: NaiveDate) -> String {
+    let settings = app.state::<SettingsStore>().get().periodic;
+    match kind.template_name(&settings).map(|name| templates::load_template(app, name)) {
+        Some(Ok(template)) => templates::expand(&template.body, local_time_on(start)).0,
+        _ => kind.default_content(start),
+    }
+}
+
+/// Return the note for the period containing `date`, creating it if needed
+pub fn open_or_create(app: &AppHandle, kind: PeriodKind, date: NaiveDate) -> Result<PeriodicNote, String> {
+    let notes_dir = ensure_notes_dir(app)?;
+    let start = kind.period_start(date);
+    let id = kind.note_id(start);
+    let path = notes_dir.join(format!("{}.md", id));
+
+    let created = if path.exists() {
+        false
+    } else {
+        // The note is written in full under a temp name, then linked into place. `hard_link`
+        // fails if the note exists, so a concurrent caller keeps the first note and nobody
+        // opens one that is still empty.
+        let tmp = notes_dir.join(format!(".{}.{}-{}.tmp", id, std::process::id(), ATTEMPT.fetch_add(1, Ordering::Relaxed)));
+        fs::write(&tmp, render(app, kind, start)).map_err(|e| e.to_string())?;
+        let linked = fs::hard_link(&tmp, &path);
+        let _ = fs::remove_file(&tmp);
+        match linked {
+            Ok(()) => true,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => false,
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let note = read_note_meta(&path, &content)?;
+
+    Ok(PeriodicNote { note, created })
+}
+
+/// Today's daily note, creating it if needed
+pub fn open_today(app: &AppHandle) -> Result<PeriodicNote, String> {
+    open_or_create(app, PeriodKind::Daily, Local::now().date_naive())
+}
+
+#[tauri::command]
+pub async fn open_periodic_note(
+    app: AppHandle,
+    kind: PeriodKind,
+    date: Option<String>,
+) -> Result<PeriodicNote, String> {
+    let date = parse_date(date.as_deref())?;
+    open_or_create(&app, kind, date)
+}
@@ -0,0 +1,404 @@
+use crate::capture;
+use crate::commands::{load_notes, remove_if_empty};
+use crate::export::get_unique_path;
+use crate::images::ImageIndex;
+use crate::note_state;
+use crate::settings::{PolicyMode, RetentionSettings, SettingsStore};
+use crate::storage::{
+    ensure_trash_dir, get_attachments_dir, get_images_dir, get_notes_dir, get_templates_dir, get_trash_dir, is_note_empty,
+};
+use crate::window_state::WindowStates;
+use crate::windows::note_window_label;
+use crate::writer::WriteQueue;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Let the windows and tray come up before the first pass
+const STARTUP_DELAY: Duration = Duration::from_secs(10);
+
+/// How often the scheduler checks whether the next pass is due
+const TICK: Duration = Duration::from_secs(60);
+
+pub(crate) const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Prefix of image references in note content
+pub const IMAGE_URL_PREFIX: &str = "gravity-image://";
+
+/// Prefix of attachment links in note content; the file name after it is percent-encoded
+pub const ATTACHMENT_URL_PREFIX: &str = "gravity-attachment://";
+
+/// What one policy removed, or would remove when it ran as a dry run
+#[derive(Debug, Serialize, Clone)]
+pub struct PolicyReport {
+    pub mode: PolicyMode,
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RetentionReport {
+    pub empty_notes: PolicyReport,
+    pub trash: PolicyReport,
+    pub orphaned_images: PolicyReport,
+    /// Follows the orphaned image policy and grace period
+    pub orphaned_attachments: PolicyReport,
+    pub history: PolicyReport,
+}
+
+/// Whether `path` was last modified more than `max_age` ago. Unknown ages count as
+/// recent, so nothing is removed by mistake.
+pub(crate) fn older_than(path: &Path, max_age: Duration, now: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok())
+        .is_some_and(|age| age > max_age)
+}
+
+pub(crate) fn files_in(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn is_markdown(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("md")
+}
+
+pub(crate) fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Move a file into the trash, stamping it so the purge age counts from now
+pub fn move_to_trash(app: &AppHandle, path: &Path) -> Result<PathBuf, String> {
+    let trash_dir = ensure_trash_dir(app)?;
+    let target = get_unique_path(trash_dir.join(file_name(path)));
+
+    fs::rename(path, &target).map_err(|e| e.to_string())?;
+    if let Ok(file) = File::options().write(true).open(&target) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+    Ok(target)
+}
+
+/// Delete empty notes last modified more than `max_age` ago. They hold nothing, so they
+/// skip the trash. Notes open in a window or with an edit queued are being written, so they stay.
+pub fn remove_empty_notes(app: &AppHandle, max_age: Duration, dry_run: bool) -> Vec<String> {
+    let notes_dir = get_notes_dir(app);
+    let queue = app.state::<WriteQueue>();
+    let in_capture = capture::open_notes(app);
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+
+    for path in files_in(&notes_dir).into_iter().filter(|p| is_markdown(p)) {
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        if !is_note_empty(&content) || !older_than(&path, max_age, now) {
+            continue;
+        }
+
+        let id = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        if in_capture.contains(&id) || app.get_webview_window(&note_window_label(&id)).is_some() || queue.is_queued(&id) {
+            continue;
+        }
+        if !dry_run {
+            match queue.delete(&id, || remove_if_empty(&notes_dir, &id)) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    log::warn!("Failed to remove empty note {}: {}", id, e);
+                    continue;
+                }
+            }
+            let _ = note_state::remove_state(&notes_dir, &id);
+        }
+        removed.push(id);
+    }
+
+    removed
+}
+
+/// Permanently delete trash entries older than `max_age`
+fn purge_trash(trash_dir: &Path, max_age: Duration, dry_run: bool) -> Vec<String> {
+    let now = SystemTime::now();
+    let mut purged = Vec::new();
+
+    for path in files_in(trash_dir) {
+        if !older_than(&path, max_age, now) {
+            continue;
+        }
+        if !dry_run {
+            let result = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+            if let Err(e) = result {
+                log::warn!("Failed to purge {}: {}", path.display(), e);
+                continue;
+            }
+        }
+        purged.push(file_name(&path));
+    }
+
+    purged
+}
+
+/// Byte ranges of the file names following `prefix` in a note's content
+fn reference_spans<'a>(content: &'a str, prefix: &'static str) -> impl Iterator<Item = Range<usize>> + 'a {
+    content.match_indices(prefix).map(move |(start, _)| {
+        let start = start + prefix.len();
+        let end = content[start..]
+            .find(|c: char| c == ')' || c == '"' || c == '\'' || c.is_whitespace())
+            .map_or(content.len(), |end| start + end);
+        start..end
+    })
+}
+
+/// Byte ranges of the image file names a note's content refers to
+pub fn image_reference_spans(content: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    reference_spans(content, IMAGE_URL_PREFIX)
+}
+
+/// File names of the images a note's content refers to, in order of appearance
+pub fn image_references(content: &str) -> impl Iterator<Item = &str> {
+    image_reference_spans(content).map(move |span| &content[span])
+}
+
+/// File names of the attachments a note's content refers to, in order of appearance
+pub fn attachment_references(content: &str) -> impl Iterator<Item = String> + '_ {
+    reference_spans(content, ATTACHMENT_URL_PREFIX).map(move |span| decode_attachment_name(&content[span]))
+}
+
+/// Escape the characters that would end a link target or break the reference scan
+pub fn encode_attachment_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '%' | '(' | ')' | '"' | '\'' | '<' | '>' => encoded.push_str(&format!("%{:02X}", c as u32)),
+            c if c.is_whitespace() => {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    encoded.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+pub fn decode_attachment_name(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Every file name `references` finds in the Markdown files in `dirs`
+fn referenced(dirs: &[PathBuf], references: impl Fn(&str) -> Vec<String>) -> HashSet<String> {
+    let mut referenced = HashSet::new();
+
+    for path in dirs.iter().flat_map(|dir| files_in(dir)).filter(|p| is_markdown(p)) {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        referenced.extend(references(&content));
+    }
+
+    referenced
+}
+
+/// File names of every image referenced from the Markdown files in `dirs`
+pub fn referenced_images(dirs: &[PathBuf]) -> HashSet<String> {
+    referenced(dirs, |content| image_references(content).map(String::from).collect())
+}
+
+/// File names of every attachment referenced from the Markdown files in `dirs`
+pub fn referenced_attachments(dirs: &[PathBuf]) -> HashSet<String> {
+    referenced(dirs, |content| attachment_references(content).collect())
+}
+
+/// Files in `dir` that `referenced` doesn't name, skipping ones newer than `min_age`
+fn unreferenced_files(dir: &Path, referenced: &HashSet<String>, min_age: Duration) -> Vec<PathBuf> {
+    let now = SystemTime::now();
+
+    let mut orphans: Vec<PathBuf> = files_in(dir)
+        .into_iter()
+        .filter(|path| path.is_file() && !referenced.contains(&file_name(path)))
+        .filter(|path| older_than(path, min_age, now))
+        .collect();
+    orphans.sort();
+    orphans
+}
+
+/// Directories whose Markdown files keep the images and attachments they refer to
+fn referencing_dirs(app: &AppHandle) -> [PathBuf; 3] {
+    [get_notes_dir(app), get_trash_dir(app), get_templates_dir(app)]
+}
+
+/// Images no note or template refers to, skipping ones newer than `min_age` that may belong
+/// to an unsaved edit. Notes in the trash count, so restoring a note keeps its images.
+pub fn orphaned_images(app: &AppHandle, min_age: Duration) -> Vec<PathBuf> {
+    let referenced = referenced_images(&referencing_dirs(app));
+    unreferenced_files(&get_images_dir(app), &referenced, min_age)
+}
+
+/// Attachments no note or template refers to, with the same grace period and trash rules as images
+pub fn orphaned_attachments(app: &AppHandle, min_age: Duration) -> Vec<PathBuf> {
+    let referenced = referenced_attachments(&referencing_dirs(app));
+    unreferenced_files(&get_attachments_dir(app), &referenced, min_age)
+}
+
+fn trash_orphaned_images(app: &AppHandle, min_age: Duration, dry_run: bool) -> Vec<String> {
+    let mut moved = Vec::new();
+
+    for path in orphaned_images(app, min_age) {
+        if !dry_run {
+            if let Err(e) = move_to_trash(app, &path) {
+                log::warn!("Failed to move orphaned image {} to trash: {}", path.display(), e);
+                continue;
+            }
+        }
+        moved.push(file_name(&path));
+    }
+    if !dry_run {
+        app.state::<ImageIndex>().forget(app, &moved);
+    }
+
+    moved
+}
+
+fn trash_orphaned_attachments(app: &AppHandle, min_age: Duration, dry_run: bool) -> Vec<String> {
+    let mut moved = Vec::new();
+
+    for path in orphaned_attachments(app, min_age) {
+        if !dry_run {
+            if let Err(e) = move_to_trash(app, &path) {
+                log::warn!("Failed to move orphaned attachment {} to trash: {}", path.display(), e);
+                continue;
+            }
+        }
+        moved.push(file_name(&path));
+    }
+
+    moved
+}
+
+/// Drop note state and window placement kept for notes that no longer exist
+fn compact_history(app: &AppHandle, dry_run: bool) -> Result<Vec<String>, String> {
+    // Listing the notes reconciles renames first, so their state isn't dropped
+    let present: HashSet<String> = load_notes(app, true)?.into_iter().map(|note| note.id).collect();
+
+    let mut stale = note_state::compact(&get_notes_dir(app), &present, dry_run)?;
+
+    let windows = app.state::<WindowStates>();
+    stale.extend(windows.compact(&present, dry_run));
+    if !dry_run {
+        windows.save(app)?;
+    }
+
+    stale.sort();
+    stale.dedup();
+    Ok(stale)
+}
+
+fn log_policy(name: &str, report: &PolicyReport) {
+    if report.items.is_empty() {
+        return;
+    }
+    match report.mode {
+        PolicyMode::DryRun => log::info!("Retention ({}, dry run) would remove: {:?}", name, report.items),
+        _ => log::info!("Retention ({}) removed: {:?}", name, report.items),
+    }
+}
+
+/// Run every policy that isn't off. With `preview`, every policy runs as a dry run,
+/// including those that are off, to show what enabling them would do.
+pub fn run(app: &AppHandle, settings: &RetentionSettings, preview: bool) -> RetentionReport {
+    let mode = |mode: PolicyMode| match (preview, mode) {
+        (true, _) => PolicyMode::DryRun,
+        (false, mode) => mode,
+    };
+    let policy = |name: &str, mode: PolicyMode, apply: &dyn Fn(bool) -> Vec<String>| {
+        let items = match mode {
+            PolicyMode::Off => Vec::new(),
+            mode => apply(mode == PolicyMode::DryRun),
+        };
+        let report = PolicyReport { mode, items };
+        log_policy(name, &report);
+        report
+    };
+
+    let empty_age = Duration::from_secs(u64::from(settings.empty_note_minutes) * 60);
+
+    let report = RetentionReport {
+        empty_notes: policy("empty notes", mode(settings.empty_notes), &|dry_run| {
+            remove_empty_notes(app, empty_age, dry_run)
+        }),
+        trash: policy("trash", mode(settings.trash), &|dry_run| {
+            purge_trash(&get_trash_dir(app), DAY * settings.trash_days, dry_run)
+        }),
+        orphaned_images: policy("orphaned images", mode(settings.orphaned_images), &|dry_run| {
+            trash_orphaned_images(app, DAY * settings.orphaned_image_days, dry_run)
+        }),
+        orphaned_attachments: policy("orphaned attachments", mode(settings.orphaned_images), &|dry_run| {
+            trash_orphaned_attachments(app, DAY * settings.orphaned_image_days, dry_run)
+        }),
+        history: policy("history", mode(settings.history), &|dry_run| {
+            compact_history(app, dry_run).unwrap_or_else(|e| {
+                log::error!("Failed to compact history: {}", e);
+                Vec::new()
+            })
+        }),
+    };
+
+    if report.empty_notes.mode == PolicyMode::On && !report.empty_notes.items.is_empty() {
+        let _ = app.emit("notes-changed", ());
+    }
+
+    report
+}
+
+/// Run the policies shortly after startup and then every `interval_minutes`
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(STARTUP_DELAY);
+        let mut last_run: Option<Instant> = None;
+
+        loop {
+            let settings = app.state::<SettingsStore>().get().retention;
+            let interval = Duration::from_secs(u64::from(settings.interval_minutes) * 60);
+
+            // The interval is re-read every tick, so a settings change applies right away
+            if !matches!(last_run, Some(last) if last.elapsed() < interval) {
+                run(&app, &settings, false);
+                last_run = Some(Instant::now());
+            }
+            std::thread::sleep(TICK);
+        }
+    });
+}
+
+/// Run the retention policies now; `preview` reports what every policy would remove
+#[tauri::command]
+pub async fn run_retention(app: AppHandle, preview: Option<bool>) -> Result<RetentionReport, String> {
+    let settings = app.state::<SettingsStore>().get().retention;
+    Ok(run(&app, &settings, preview.unwrap_or(false)))
+}
@@ -0,0 +1,660 @@
+use crate::markdown::{escape, escape_line_start};
+use encoding_rs::Encoding;
+use std::collections::HashMap;
+
+/// Destinations whose text is never part of the document body
+const SKIPPED_DESTINATIONS: &[&str] = &[
+    "fonttbl", "colortbl", "info", "pict", "shppict", "nonshppict", "object", "header", "headerl", "headerr", "headerf",
+    "footer", "footerl", "footerr", "footerf", "footnote", "annotation", "themedata", "colorschememapping",
+    "latentstyles", "datastore", "xmlnstbl", "listtable", "listoverridetable", "rsidtbl", "generator", "filetbl",
+    "revtbl", "pgdsctbl", "bkmkstart", "bkmkend", "xe", "tc", "pn",
+];
+
+/// Where the text of the current group goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Destination {
+    Body,
+    Skip,
+    /// A field's instruction, e.g. `HYPERLINK "https://..."`
+    FieldInstruction,
+    /// The marker Word and TextEdit write before a list item, e.g. `•` or `1.`
+    ListText,
+    Stylesheet,
+}
+
+/// Formatting that RTF scopes to `{...}` groups
+#[derive(Debug, Clone)]
+struct GroupState {
+    destination: Destination,
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    /// Fallback characters to skip after each `\u`
+    unicode_skip: usize,
+    link: Option<String>,
+}
+
+impl Default for GroupState {
+    fn default() -> Self {
+        Self {
+            destination: Destination::Body,
+            bold: false,
+            italic: false,
+            strike: false,
+            unicode_skip: 1,
+            link: None,
+        }
+    }
+}
+
+/// A stretch of text with one set of formatting
+struct Run {
+    text: String,
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    link: Option<String>,
+}
+
+impl Run {
+    fn same_format(&self, other: &GroupState) -> bool {
+        self.bold == other.bold && self.italic == other.italic && self.strike == other.strike && self.link == other.link
+    }
+}
+
+/// A `\field` being read, closed when its group ends
+struct Field {
+    depth: usize,
+    instruction: String,
+}
+
+/// Numbering of one level of an open list
+struct ListLevel {
+    ordered: bool,
+    number: u32,
+    /// Width of the last marker, which nested items are indented by
+    width: usize,
+}
+
+#[derive(Default)]
+struct Paragraph {
+    runs: Vec<Run>,
+    list_text: String,
+    list_level: Option<usize>,
+    heading: Option<usize>,
+    in_table: bool,
+}
+
+#[derive(Default)]
+struct Converter {
+    groups: Vec<GroupState>,
+    state: GroupState,
+    paragraph: Paragraph,
+    blocks: Vec<String>,
+    /// The previous block was a list item, so the next one joins without a blank line
+    in_list: bool,
+    /// The open list levels, outermost first
+    list_levels: Vec<ListLevel>,
+    row: Vec<String>,
+    cell: Vec<String>,
+    table: Vec<Vec<String>>,
+    fields: Vec<Field>,
+    /// Heading levels of paragraph styles named "heading N"
+    heading_styles: HashMap<i32, usize>,
+    style_number: Option<i32>,
+    style_name: String,
+    /// `\*` was seen: the next destination can be skipped if it isn't one we know
+    ignorable: bool,
+    /// Fallback characters still to skip after a `\u`
+    skip: usize,
+    high_surrogate: Option<u16>,
+    /// Text bytes not decoded yet; double-byte code pages split characters across `\'hh` escapes
+    bytes: Vec<u8>,
+    /// The document's code page, from `\ansicpg`
+    encoding: Option<&'static Encoding>,
+}
+
+/// The encoding of a Windows code page number; ones encoding_rs doesn't know return None
+fn code_page(number: i32) -> Option<&'static Encoding> {
+    Some(match number {
+        874 => encoding_rs::WINDOWS_874,
+        932 => encoding_rs::SHIFT_JIS,
+        936 => encoding_rs::GBK,
+        949 => encoding_rs::EUC_KR,
+        950 => encoding_rs::BIG5,
+        866 => encoding_rs::IBM866,
+        1250 => encoding_rs::WINDOWS_1250,
+        1251 => encoding_rs::WINDOWS_1251,
+        1252 => encoding_rs::WINDOWS_1252,
+        1253 => encoding_rs::WINDOWS_1253,
+        1254 => encoding_rs::WINDOWS_1254,
+        1255 => encoding_rs::WINDOWS_1255,
+        1256 => encoding_rs::WINDOWS_1256,
+        1257 => encoding_rs::WINDOWS_1257,
+        1258 => encoding_rs::WINDOWS_1258,
+        10000 => encoding_rs::MACINTOSH,
+        65001 => encoding_rs::UTF_8,
+        _ => return None,
+    })
+}
+
+/// Markdown delimiters for a run's formatting, as (opening, closing)
+fn delimiters(run: &Run) -> (String, String) {
+    let mut open = String::new();
+    if run.strike {
+        open.push_str("~~");
+    }
+    if run.bold {
+        open.push_str("**");
+    }
+    if run.italic {
+        open.push('*');
+    }
+    let close = open.chars().rev().collect();
+    (open, close)
+}
+
+/// Inline Markdown for a paragraph's runs, with `\line` breaks kept as newlines
+fn render_runs(runs: &[Run]) -> String {
+    let mut out = String::new();
+    for run in runs {
+        let text = escape(&run.text);
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            out.push_str(&text);
+            continue;
+        }
+
+        let (open, close) = delimiters(run);
+        let inner = match &run.link {
+            Some(url) => format!("[{}]({})", trimmed, url.replace(' ', "%20")),
+            None => trimmed.to_string(),
+        };
+        out.push_str(&text[..text.len() - text.trim_start().len()]);
+        out.push_str(&open);
+        out.push_str(&inner);
+        out.push_str(&close);
+        out.push_str(&text[text.trim_end().len()..]);
+    }
+    out
+}
+
+/// URL of a `HYPERLINK` field instruction; bookmark links (`\l`) have none
+fn hyperlink_target(instruction: &str) -> Option<String> {
+    let rest = instruction.trim().strip_prefix("HYPERLINK")?.trim_start();
+    if rest.starts_with("\\l") {
+        return None;
+    }
+    let url = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => rest.split_whitespace().next()?,
+    };
+    (!url.is_empty()).then(|| url.to_string())
+}
+
+impl Converter {
+    /// Queue a text byte for decoding with the document's code page
+    fn push_byte(&mut self, byte: u8) {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return;
+        }
+        self.bytes.push(byte);
+    }
+
+    /// Decode the queued text bytes; called before anything that isn't another text byte
+    fn flush_bytes(&mut self) {
+        if self.bytes.is_empty() {
+            return;
+        }
+        let bytes = std::mem::take(&mut self.bytes);
+        let encoding = self.encoding.unwrap_or(encoding_rs::WINDOWS_1252);
+        let (text, _) = encoding.decode_without_bom_handling(&bytes);
+        for c in text.chars() {
+            self.push_char(c);
+        }
+    }
+
+    fn push_char(&mut self, c: char) {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return;
+        }
+        match self.state.destination {
+            Destination::Body => match self.paragraph.runs.last_mut() {
+                Some(run) if run.same_format(&self.state) => run.text.push(c),
+                _ => self.paragraph.runs.push(Run {
+                    text: c.to_string(),
+                    bold: self.state.bold,
+                    italic: self.state.italic,
+                    strike: self.state.strike,
+                    link: self.state.link.clone(),
+                }),
+            },
+            Destination::FieldInstruction => {
+                if let Some(field) = self.fields.last_mut() {
+                    field.instruction.push(c);
+                }
+            }
+            Destination::ListText => self.paragraph.list_text.push(c),
+            Destination::Stylesheet if c == ';' => self.end_style(),
+            Destination::Stylesheet => self.style_name.push(c),
+            Destination::Skip => {}
+        }
+    }
+
+    fn push_unicode(&mut self, value: i32) {
+        let unit = if value < 0 { (value + 65536) as u16 } else { value as u16 };
+        let c = match (self.high_surrogate.take(), unit) {
+            (None, 0xd800..=0xdbff) => {
+                self.high_surrogate = Some(unit);
+                None
+            }
+            (Some(high), 0xdc00..=0xdfff) => char::decode_utf16([high, unit]).next().and_then(Result::ok),
+            (_, unit) => char::from_u32(unit as u32),
+        };
+        self.skip = 0;
+        if let Some(c) = c {
+            self.push_char(c);
+        }
+        self.skip = self.state.unicode_skip;
+    }
+
+    fn end_style(&mut self) {
+        let name = self.style_name.trim().to_ascii_lowercase();
+        if let (Some(number), Some(level)) = (self.style_number, name.strip_prefix("heading ")) {
+            if let Ok(level) = level.trim().parse::<usize>() {
+                self.heading_styles.insert(number, level.clamp(1, 6));
+            }
+        }
+        self.style_name.clear();
+    }
+
+    fn open_group(&mut self) {
+        self.flush_bytes();
+        self.groups.push(self.state.clone());
+        if self.state.destination == Destination::Stylesheet {
+            // Each style is its own group and `\s0` (Normal) may be implied
+            self.style_number = Some(0);
+            self.style_name.clear();
+        }
+    }
+
+    fn close_group(&mut self) {
+        self.flush_bytes();
+        if self.state.destination == Destination::Stylesheet && !self.style_name.trim().is_empty() {
+            self.end_style();
+        }
+        self.state = self.groups.pop().unwrap_or_default();
+        let depth = self.groups.len();
+        while self.fields.last().is_some_and(|field| field.depth > depth) {
+            self.fields.pop();
+        }
+    }
+
+    fn control_word(&mut self, word: &str, param: Option<i32>) {
+        self.flush_bytes();
+        let ignorable = std::mem::take(&mut self.ignorable);
+        let on = param != Some(0);
+
+        match word {
+            "fldinst" => self.state.destination = Destination::FieldInstruction,
+            "fldrslt" => {
+                self.state.destination = Destination::Body;
+                self.state.link = self.fields.last().and_then(|field| hyperlink_target(&field.instruction));
+            }
+            "field" => self.fields.push(Field { depth: self.groups.len(), instruction: String::new() }),
+            "listtext" | "pntext" => self.state.destination = Destination::ListText,
+            "stylesheet" => self.state.destination = Destination::Stylesheet,
+            "s" if self.state.destination == Destination::Stylesheet => self.style_number = param,
+            "cs" | "ds" | "ts" if self.state.destination == Destination::Stylesheet => self.style_number = None,
+            _ if SKIPPED_DESTINATIONS.contains(&word) => self.state.destination = Destination::Skip,
+            _ if ignorable => self.state.destination = Destination::Skip,
+            _ if self.state.destination == Destination::Skip => {}
+            // List markers and field instructions carry their own paragraph and font settings
+            "pard" | "ls" | "ilvl" | "outlinelevel" | "s" | "intbl" | "cell" | "row" | "par"
+                if self.state.destination != Destination::Body => {}
+
+            "ansicpg" => self.encoding = param.and_then(code_page),
+            "mac" => self.encoding = Some(encoding_rs::MACINTOSH),
+            "uc" => self.state.unicode_skip = param.unwrap_or(1).max(0) as usize,
+            "u" => self.push_unicode(param.unwrap_or(0)),
+            "b" => self.state.bold = on,
+            "i" => self.state.italic = on,
+            "strike" | "striked" => self.state.strike = on,
+            "plain" => {
+                self.state.bold = false;
+                self.state.italic = false;
+                self.state.strike = false;
+            }
+            "par" | "sect" | "page" => self.end_paragraph(),
+            "line" => self.push_char('\n'),
+            "tab" => self.push_char(if self.state.destination == Destination::ListText { ' ' } else { '\t' }),
+            "emdash" => self.push_char('—'),
+            "endash" => self.push_char('–'),
+            "bullet" => self.push_char('•'),
+            "lquote" => self.push_char('‘'),
+            "rquote" => self.push_char('’'),
+            "ldblquote" => self.push_char('“'),
+            "rdblquote" => self.push_char('”'),
+            "pard" => {
+                self.paragraph.list_level = None;
+                self.paragraph.heading = None;
+                self.paragraph.in_table = false;
+            }
+            "ls" => {
+                self.paragraph.list_level.get_or_insert(0);
+            }
+            "ilvl" => self.paragraph.list_level = Some(param.unwrap_or(0).clamp(0, 8) as usize),
+            "outlinelevel" => self.paragraph.heading = param.filter(|level| (0..6).contains(level)).map(|level| level as usize + 1),
+            "s" => {
+                if let Some(level) = param.and_then(|number| self.heading_styles.get(&number)) {
+                    self.paragraph.heading = Some(*level);
+                }
+            }
+            "intbl" => self.paragraph.in_table = true,
+            "cell" => self.end_cell(),
+            "row" => self.end_row(),
+            _ => {}
+        }
+    }
+
+    fn control_symbol(&mut self, symbol: u8) {
+        if matches!(symbol, b'\\' | b'{' | b'}') {
+            // Can be the second byte of a double-byte character
+            self.push_byte(symbol);
+            return;
+        }
+        self.flush_bytes();
+        match symbol {
+            b'*' => self.ignorable = true,
+            b'~' => self.push_char(' '),
+            b'_' => self.push_char('-'),
+            b'\n' | b'\r' => self.end_paragraph(),
+            // Optional hyphens and formula characters
+            _ => {}
+        }
+    }
+
+    /// Close the current paragraph, turning it into a heading, list item, table cell or paragraph
+    fn end_paragraph(&mut self) {
+        let paragraph = std::mem::take(&mut self.paragraph);
+        // Paragraph properties last until the next `\pard`
+        self.paragraph.list_level = paragraph.list_level;
+        self.paragraph.heading = paragraph.heading;
+        self.paragraph.in_table = paragraph.in_table;
+
+        let mut runs = paragraph.runs;
+        if paragraph.heading.is_some() && paragraph.list_level.is_none() {
+            // Headings are usually bold already; the `#` says enough
+            runs.iter_mut().for_each(|run| run.bold = false);
+        }
+        let text = render_runs(&runs);
+        let lines: Vec<&str> = text.lines().map(|line| line.trim_matches([' ', '\t'])).filter(|line| !line.is_empty()).collect();
+
+        if paragraph.in_table {
+            if !lines.is_empty() {
+                self.cell.push(lines.join("<br>"));
+            }
+            return;
+        }
+        self.end_table();
+
+        if lines.is_empty() {
+            return;
+        }
+
+        let list_text = paragraph.list_text;
+        let marker = list_text.trim();
+        let level = paragraph.list_level.or((!marker.is_empty()).then_some(0));
+        let block = match (level, paragraph.heading) {
+            (Some(level), _) => {
+                self.list_item(level, marker, &lines.join(" "))
+            }
+            (None, Some(heading)) => format!("{} {}", "#".repeat(heading), lines.join(" ")),
+            (None, None) => lines.iter().map(|line| escape_line_start(line)).collect::<Vec<_>>().join("\\\n"),
+        };
+
+        let is_list = level.is_some();
+        if !is_list {
+            self.list_levels.clear();
+        }
+        if !self.blocks.is_empty() && (!is_list || !self.in_list) {
+            self.blocks.push(String::new());
+        }
+        self.blocks.push(block);
+        self.in_list = is_list;
+    }
+
+    /// A list item line, indented under its parent items. Markers with a number or ending
+    /// in `.` or `)` (e.g. `3.`, `b)`) are ordered; bullets such as `•` or Word's `o` are not.
+    fn list_item(&mut self, level: usize, list_text: &str, text: &str) -> String {
+        let ordered = list_text.contains(|c: char| c.is_ascii_digit()) || list_text.ends_with(['.', ')']);
+        let written: Option<u32> = list_text.chars().filter(char::is_ascii_digit).collect::<String>().parse().ok();
+
+        self.list_levels.truncate(level + 1);
+        while self.list_levels.len() <= level {
+            self.list_levels.push(ListLevel { ordered, number: 0, width: 2 });
+        }
+
+        let indent: usize = self.list_levels[..level].iter().map(|list| list.width).sum();
+        let list = &mut self.list_levels[level];
+        if list.ordered != ordered {
+            *list = ListLevel { ordered, number: 0, width: 2 };
+        }
+        list.number = written.unwrap_or(list.number + 1);
+        let marker = if ordered { format!("{}. ", list.number) } else { "- ".to_string() };
+        list.width = marker.len();
+
+        format!("{}{}{}", " ".repeat(indent), marker, text)
+    }
+
+    fn end_cell(&mut self) {
+        // Text after the last `\par` in a cell is still open
+        if !self.paragraph.runs.is_empty() {
+            self.paragraph.in_table = true;
+            self.end_paragraph();
+        }
+        let cell = std::mem::take(&mut self.cell).join("<br>").replace('|', "\\|");
+        self.row.push(cell);
+    }
+
+    fn end_row(&mut self) {
+        let row = std::mem::take(&mut self.row);
+        if !row.is_empty() {
+            self.table.push(row);
+        }
+    }
+
+    /// Write out a finished table, using its first row as the header
+    fn end_table(&mut self) {
+        let rows = std::mem::take(&mut self.table);
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        let mut lines = Vec::new();
+        for (index, mut row) in rows.into_iter().enumerate() {
+            row.resize(columns, String::new());
+            lines.push(format!("| {} |", row.join(" | ")));
+            if index == 0 {
+                lines.push(format!("|{}", " --- |".repeat(columns)));
+            }
+        }
+
+        if !self.blocks.is_empty() {
+            self.blocks.push(String::new());
+        }
+        self.blocks.push(lines.join("\n"));
+        self.in_list = false;
+        self.list_levels.clear();
+    }
+}
+
+/// Convert an RTF document to Markdown, keeping emphasis, links, lists, headings and tables
+pub fn rtf_to_markdown(rtf: &[u8]) -> String {
+    let mut converter = Converter::default();
+    let mut i = 0;
+
+    while i < rtf.len() {
+        match rtf[i] {
+            b'{' => {
+                converter.open_group();
+                i += 1;
+            }
+            b'}' => {
+                converter.close_group();
+                i += 1;
+            }
+            b'\\' => {
+                let start = i + 1;
+                let Some(&next) = rtf.get(start) else {
+                    break;
+                };
+
+                if next.is_ascii_alphabetic() {
+                    let word_end = start + rtf[start..].iter().take_while(|b| b.is_ascii_alphabetic()).count();
+                    let number_start = word_end + usize::from(rtf.get(word_end) == Some(&b'-'));
+                    let number_end = number_start + rtf[number_start..].iter().take_while(|b| b.is_ascii_digit()).count();
+                    let word = String::from_utf8_lossy(&rtf[start..word_end]).into_owned();
+                    let param = if number_end > number_start {
+                        std::str::from_utf8(&rtf[word_end..number_end]).ok().and_then(|n| n.parse::<i32>().ok())
+                    } else {
+                        None
+                    };
+                    let end = if number_end > number_start { number_end } else { word_end };
+                    // A single space ends the control word and is not part of the text
+                    i = end + usize::from(rtf.get(end) == Some(&b' '));
+
+                    if word == "bin" {
+                        i += param.unwrap_or(0).max(0) as usize;
+                        continue;
+                    }
+                    converter.control_word(&word, param);
+                } else if next == b'\'' {
+                    let hex = rtf.get(start + 1..start + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+                    if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                        converter.push_byte(byte);
+                    }
+                    i = start + 3;
+                } else {
+                    converter.control_symbol(next);
+                    i = start + 1;
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            byte => {
+                converter.push_byte(byte);
+                i += 1;
+            }
+        }
+    }
+
+    converter.flush_bytes();
+    converter.end_paragraph();
+    converter.end_table();
+    converter.blocks.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::clean_markdown;
+    use std::fs;
+    use std::path::Path;
+
+    fn convert(rtf: &str) -> String {
+        clean_markdown(&rtf_to_markdown(rtf.as_bytes()))
+    }
+
+    /// Each `tests/fixtures/clip/*.rtf` must convert to the Markdown in the `.md` beside it
+    #[test]
+    fn clip_fixtures() {
+        let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/clip"));
+        let mut checked = 0;
+        let mut failures = Vec::new();
+
+        for entry in fs::read_dir(dir).expect("fixture dir") {
+            let path = entry.expect("fixture entry").path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("rtf") {
+                continue;
+            }
+            let rtf = fs::read(&path).expect("read fixture");
+            let expected = fs::read_to_string(path.with_extension("md")).expect("expected markdown");
+            let actual = clean_markdown(&rtf_to_markdown(&rtf));
+            if actual != expected.trim_end() {
+                failures.push(format!("{}:\n--- expected\n{}\n--- actual\n{}", path.display(), expected.trim_end(), actual));
+            }
+            checked += 1;
+        }
+
+        assert!(checked > 0, "no fixtures in {}", dir.display());
+        assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+    }
+
+    #[test]
+    fn emphasis_is_scoped_to_groups() {
+        assert_eq!(convert(r"{\rtf1 plain {\b bold {\i both}} \strike gone\strike0  done\par}"), "plain **bold** ***both*** ~~gone~~ done");
+    }
+
+    #[test]
+    fn code_page_defaults_to_windows_1252() {
+        assert_eq!(convert(r"{\rtf1\ansi caf\'e9 \'93quoted\'94 \'80\par}"), "café “quoted” €");
+    }
+
+    #[test]
+    fn ansicpg_picks_the_code_page() {
+        assert_eq!(convert(r"{\rtf1\ansi\ansicpg1251 \'cf\'f0\'e8\'e2\'e5\'f2\par}"), "Привет");
+        assert_eq!(convert(r"{\rtf1\ansi\ansicpg1253 \'e1\'e2\'e3\par}"), "αβγ");
+    }
+
+    #[test]
+    fn double_byte_characters_span_escapes() {
+        assert_eq!(convert(r"{\rtf1\ansi\ansicpg932 \'93\'fa\'96\'7b\'8c\'ea\par}"), "日本語");
+        // The trail byte of 表 is a backslash
+        assert_eq!(convert(r"{\rtf1\ansi\ansicpg932 \'95\\\'8e\'a6\par}"), "表示");
+        assert_eq!(convert(r"{\rtf1\ansi\ansicpg936 \'d6\'d0\'ce\'c4\par}"), "中文");
+    }
+
+    #[test]
+    fn unicode_escapes_skip_their_fallback() {
+        assert_eq!(convert(r"{\rtf1 \u8212?dash \uc2\u26085\'93\'fa day\par}"), "—dash 日 day");
+        assert_eq!(convert(r"{\rtf1 \u-10179?\u-8704?\par}"), "😀");
+    }
+
+    #[test]
+    fn escaped_symbols_and_special_words() {
+        assert_eq!(convert(r"{\rtf1 \{a\} \\ b\~c\_d \ldblquote e\rdblquote\emdash f\par}"), r"{a} \\ b c-d “e”—f");
+    }
+
+    #[test]
+    fn hyperlink_fields_become_links() {
+        let rtf = r#"{\rtf1 See {\field{\*\fldinst{HYPERLINK "https://example.com/a b"}}{\fldrslt{\ul the docs}}} or {\field{\*\fldinst{HYPERLINK \\l "top"}}{\fldrslt the top}}.\par}"#;
+        assert_eq!(convert(rtf), "See [the docs](https://example.com/a%20b) or the top.");
+    }
+
+    #[test]
+    fn list_markers_decide_the_list_kind() {
+        let rtf = r"{\rtf1\pard\ls1{\listtext 1.\tab}One\par{\listtext 2.\tab}Two\par\ilvl1{\listtext o\tab}Nested\par\pard After\par}";
+        assert_eq!(convert(rtf), "1. One\n2. Two\n   - Nested\n\nAfter");
+    }
+
+    #[test]
+    fn heading_styles_come_from_the_stylesheet() {
+        let rtf = r"{\rtf1{\stylesheet{Normal;}{\s1 heading 1;}{\s2\sbasedon0 heading 2;}{\*\cs10 heading 3;}}\pard\s1\b Title\par\pard\s2 Section\par\pard\plain Body\par}";
+        assert_eq!(convert(rtf), "# Title\n\n## Section\n\nBody");
+    }
+
+    #[test]
+    fn tables_use_the_first_row_as_header() {
+        let rtf = r"{\rtf1\trowd\cellx1000\cellx2000\pard\intbl Name\cell Count\cell\row\trowd\cellx1000\cellx2000\pard\intbl a|b\cell 2\cell\row\pard After\par}";
+        assert_eq!(convert(rtf), "| Name | Count |\n| --- | --- |\n| a\\|b | 2 |\n\nAfter");
+    }
+
+    #[test]
+    fn skips_binary_data_and_unknown_destinations() {
+        let rtf = "{\\rtf1 a{\\*\\unknown hidden}b{\\pict\\bin4 }}}}}c\\par}";
+        assert_eq!(convert(rtf), "abc");
+    }
+}
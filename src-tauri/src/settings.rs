@@ -0,0 +1,460 @@
+use crate::export::get_unique_path;
+use crate::shortcuts::{parse_binding, ShortcutAction, ShortcutError};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const SETTINGS_FILENAME: &str = "config.json";
+
+/// The frontend store written by earlier versions, folded into `config.json` on first launch
+const LEGACY_STORE_FILENAME: &str = "settings.json";
+
+/// Bump when the file format changes, and add a step to `migrate`
+pub const SETTINGS_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Whether a retention policy runs, and whether it only reports what it would remove
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyMode {
+    Off,
+    DryRun,
+    #[default]
+    On,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetentionSettings {
+    /// How often the policies run after the startup pass
+    pub interval_minutes: u32,
+    pub empty_notes: PolicyMode,
+    /// Empty notes older than this are deleted
+    pub empty_note_minutes: u32,
+    pub trash: PolicyMode,
+    /// Files stay in the trash this long before they are purged
+    pub trash_days: u32,
+    pub orphaned_images: PolicyMode,
+    /// Unreferenced images older than this are moved to the trash
+    pub orphaned_image_days: u32,
+    /// Drop saved state for notes that no longer exist
+    pub history: PolicyMode,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        RetentionSettings {
+            interval_minutes: 60,
+            empty_notes: PolicyMode::On,
+            empty_note_minutes: 15,
+            trash: PolicyMode::On,
+            trash_days: 30,
+            orphaned_images: PolicyMode::DryRun,
+            orphaned_image_days: 7,
+            history: PolicyMode::On,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ImageSettings {
+    /// Larger images are scaled down to fit; `None` keeps the original size
+    pub max_dimension: Option<u32>,
+    /// Remove EXIF and XMP metadata such as GPS coordinates from stored images
+    pub strip_metadata: bool,
+    /// Quality used when a JPEG has to be re-encoded
+    pub jpeg_quality: u8,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        ImageSettings {
+            max_dimension: Some(2560),
+            strip_metadata: true,
+            jpeg_quality: 85,
+        }
+    }
+}
+
+/// Templates periodic notes are created from, by name; `None`, or a template that doesn't
+/// exist, gives a plain dated heading
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PeriodicSettings {
+    pub daily_template: Option<String>,
+    pub weekly_template: Option<String>,
+    pub monthly_template: Option<String>,
+}
+
+impl Default for PeriodicSettings {
+    fn default() -> Self {
+        PeriodicSettings {
+            daily_template: Some("daily".to_string()),
+            weekly_template: Some("weekly".to_string()),
+            monthly_template: Some("monthly".to_string()),
+        }
+    }
+}
+
+pub type ShortcutBindings = BTreeMap<ShortcutAction, Option<String>>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub theme: Theme,
+    pub editor_font_size: u32,
+    /// Global shortcut per action; `None` leaves the action unbound
+    pub shortcuts: ShortcutBindings,
+    pub retention: RetentionSettings,
+    pub images: ImageSettings,
+    pub periodic: PeriodicSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            theme: Theme::default(),
+            editor_font_size: 15,
+            shortcuts: default_shortcuts(),
+            retention: RetentionSettings::default(),
+            images: ImageSettings::default(),
+            periodic: PeriodicSettings::default(),
+        }
+    }
+}
+
+fn default_shortcuts() -> ShortcutBindings {
+    ShortcutAction::ALL
+        .into_iter()
+        .map(|action| (action, action.default_binding().map(String::from)))
+        .collect()
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SettingsError {
+    /// A value is out of range or malformed
+    Invalid { field: String, message: String },
+    /// A shortcut could not be parsed or registered
+    Shortcut { error: ShortcutError },
+    /// The file was written by a newer version of Gravity
+    UnsupportedVersion { version: u32 },
+    /// The settings file could not be read or written
+    Io { message: String },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Invalid { field, message } => write!(f, "Invalid {}: {}", field, message),
+            SettingsError::Shortcut { error } => write!(f, "{}", error),
+            SettingsError::UnsupportedVersion { version } => {
+                write!(f, "Settings version {} is newer than this version of Gravity supports", version)
+            }
+            SettingsError::Io { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<ShortcutError> for SettingsError {
+    fn from(error: ShortcutError) -> Self {
+        SettingsError::Shortcut { error }
+    }
+}
+
+fn io_error(e: impl ToString) -> SettingsError {
+    SettingsError::Io { message: e.to_string() }
+}
+
+fn invalid(field: &str, message: impl Into<String>) -> SettingsError {
+    SettingsError::Invalid { field: field.to_string(), message: message.into() }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if !(8..=48).contains(&self.editor_font_size) {
+            return Err(invalid("editor_font_size", "must be between 8 and 48"));
+        }
+        let retention = &self.retention;
+        for (field, value, min) in [
+            ("retention.interval_minutes", retention.interval_minutes, 5),
+            ("retention.empty_note_minutes", retention.empty_note_minutes, 1),
+            ("retention.trash_days", retention.trash_days, 1),
+            ("retention.orphaned_image_days", retention.orphaned_image_days, 1),
+        ] {
+            if value < min {
+                return Err(invalid(field, format!("must be at least {}", min)));
+            }
+        }
+
+        if self.images.max_dimension.is_some_and(|max| max < 256) {
+            return Err(invalid("images.max_dimension", "must be at least 256"));
+        }
+        if !(10..=100).contains(&self.images.jpeg_quality) {
+            return Err(invalid("images.jpeg_quality", "must be between 10 and 100"));
+        }
+
+        let periodic = &self.periodic;
+        for (field, template) in [
+            ("periodic.daily_template", &periodic.daily_template),
+            ("periodic.weekly_template", &periodic.weekly_template),
+            ("periodic.monthly_template", &periodic.monthly_template),
+        ] {
+            if template.as_ref().is_some_and(|name| name.contains(['/', '\\']) || name.starts_with('.')) {
+                return Err(invalid(field, "must be the name of a template"));
+            }
+        }
+
+        // Every binding must parse, and no two actions may share one
+        let mut seen: HashMap<u32, ShortcutAction> = HashMap::new();
+        for (action, binding) in &self.shortcuts {
+            let Some(binding) = binding else {
+                continue;
+            };
+            let shortcut = parse_binding(binding)?;
+            if let Some(other) = seen.insert(shortcut.id(), *action) {
+                return Err(ShortcutError::Conflict {
+                    binding: binding.clone(),
+                    owner: other.label().to_string(),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fill in actions added since the file was written and tidy bindings
+    fn normalize(mut self) -> Self {
+        for action in ShortcutAction::ALL {
+            self.shortcuts
+                .entry(action)
+                .or_insert_with(|| action.default_binding().map(String::from));
+        }
+        for binding in self.shortcuts.values_mut() {
+            *binding = binding.take().map(|b| b.trim().to_string()).filter(|b| !b.is_empty());
+        }
+        let periodic = &mut self.periodic;
+        for template in [&mut periodic.daily_template, &mut periodic.weekly_template, &mut periodic.monthly_template] {
+            *template = template.take().map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        }
+        self.version = SETTINGS_VERSION;
+        self
+    }
+}
+
+/// Upgrade a settings document one version at a time
+fn migrate(mut value: Value) -> Result<Value, SettingsError> {
+    loop {
+        let version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        value = match version {
+            0 => migrate_v0(value),
+            SETTINGS_VERSION => return Ok(value),
+            version => return Err(SettingsError::UnsupportedVersion { version }),
+        };
+    }
+}
+
+/// Version 0 is the frontend store's camelCase shape, with no shortcuts map
+fn migrate_v0(value: Value) -> Value {
+    let mut shortcuts = serde_json::Map::new();
+    for (key, action) in [("newNoteShortcut", "new_note"), ("mainWindowShortcut", "main_window")] {
+        if let Some(binding) = value.get(key).and_then(Value::as_str) {
+            shortcuts.insert(action.to_string(), json!(binding));
+        }
+    }
+
+    json!({
+        "version": 1,
+        "theme": value.get("theme").cloned().unwrap_or(json!("system")),
+        "editor_font_size": value.get("editorFontSize").cloned().unwrap_or(json!(15)),
+        "shortcuts": shortcuts,
+    })
+}
+
+/// Parse, migrate and validate a settings document
+fn from_json(json: &str) -> Result<Settings, SettingsError> {
+    let value: Value = serde_json::from_str(json).map_err(|e| invalid("file", e.to_string()))?;
+    let value = migrate(value)?;
+    let settings: Settings = serde_json::from_value(value).map_err(|e| invalid("file", e.to_string()))?;
+    let settings = settings.normalize();
+    settings.validate()?;
+    Ok(settings)
+}
+
+fn app_data_dir(app: &AppHandle) -> PathBuf {
+    app.path().app_data_dir().expect("Failed to get app data dir")
+}
+
+/// Settings from before `config.json`, kept by the frontend store
+fn load_legacy(app_data: &Path) -> Option<Value> {
+    fs::read_to_string(app_data.join(LEGACY_STORE_FILENAME))
+        .ok()
+        .and_then(|json| serde_json::from_str::<Value>(&json).ok())
+        .and_then(|store| store.get("settings").cloned())
+}
+
+fn write_settings(path: &Path, settings: &Settings) -> Result<(), SettingsError> {
+    let json = serde_json::to_string_pretty(settings).map_err(io_error)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(io_error)?;
+    fs::rename(&tmp, path).map_err(io_error)
+}
+
+/// Called with the old and new settings before a change is committed. An error aborts the
+/// change; subscribers that already ran are called again with the arguments swapped.
+pub type Subscriber = Box<dyn Fn(&AppHandle, &Settings, &Settings) -> Result<(), SettingsError> + Send + Sync>;
+
+/// The app's settings, kept in memory and written to `config.json`
+pub struct SettingsStore {
+    settings: Mutex<Settings>,
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl SettingsStore {
+    pub fn load(app: &AppHandle) -> Self {
+        let app_data = app_data_dir(app);
+        let path = app_data.join(SETTINGS_FILENAME);
+
+        let settings = match fs::read_to_string(&path) {
+            Ok(json) => from_json(&json).unwrap_or_else(|e| {
+                log::error!("Failed to load settings, using defaults: {}", e);
+                Settings::default()
+            }),
+            Err(_) => {
+                let migrated = load_legacy(&app_data).and_then(|legacy| {
+                    from_json(&legacy.to_string())
+                        .map_err(|e| log::error!("Failed to migrate old settings: {}", e))
+                        .ok()
+                });
+                let settings = migrated.unwrap_or_default();
+                if let Err(e) = write_settings(&path, &settings) {
+                    log::error!("Failed to write settings: {}", e);
+                }
+                settings
+            }
+        };
+
+        SettingsStore {
+            settings: Mutex::new(settings),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Settings> {
+        self.settings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn get(&self) -> Settings {
+        self.lock().clone()
+    }
+
+    pub fn subscribe<F>(&self, subscriber: F)
+    where
+        F: Fn(&AppHandle, &Settings, &Settings) -> Result<(), SettingsError> + Send + Sync + 'static,
+    {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Box::new(subscriber));
+    }
+
+    /// Validate `new`, let subscribers apply it, then save it and notify the windows
+    pub fn replace(&self, app: &AppHandle, new: Settings) -> Result<Settings, SettingsError> {
+        let mut current = self.lock();
+        self.apply(app, &mut current, new)
+    }
+
+    /// Change a copy of the current settings with `change` and apply it, all under one lock
+    /// so concurrent updates can't drop each other's changes
+    pub fn update<F>(&self, app: &AppHandle, change: F) -> Result<Settings, SettingsError>
+    where
+        F: FnOnce(&mut Settings),
+    {
+        let mut current = self.lock();
+        let mut new = current.clone();
+        change(&mut new);
+        self.apply(app, &mut current, new)
+    }
+
+    /// Move `current`, the locked settings, to `new`
+    fn apply(&self, app: &AppHandle, current: &mut Settings, new: Settings) -> Result<Settings, SettingsError> {
+        let new = new.normalize();
+        new.validate()?;
+
+        if *current == new {
+            return Ok(new);
+        }
+
+        let subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        for (applied, subscriber) in subscribers.iter().enumerate() {
+            if let Err(e) = subscriber(app, current, &new) {
+                for undo in subscribers[..applied].iter().rev() {
+                    if let Err(undo_error) = undo(app, &new, current) {
+                        log::error!("Failed to roll back settings change: {}", undo_error);
+                    }
+                }
+                return Err(e);
+            }
+        }
+
+        write_settings(&app_data_dir(app).join(SETTINGS_FILENAME), &new)?;
+        *current = new.clone();
+
+        let _ = app.emit("settings-changed", &new);
+        Ok(new)
+    }
+}
+
+#[tauri::command]
+pub fn get_settings(store: State<'_, SettingsStore>) -> Settings {
+    store.get()
+}
+
+#[tauri::command]
+pub fn update_settings(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    settings: Settings,
+) -> Result<Settings, SettingsError> {
+    store.replace(&app, settings)
+}
+
+/// Write the settings to `destination` as a shareable file, returning its path
+#[tauri::command]
+pub fn export_settings(store: State<'_, SettingsStore>, destination: PathBuf) -> Result<String, SettingsError> {
+    let path = get_unique_path(destination.join("gravity-settings.json"));
+    write_settings(&path, &store.get())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Replace the settings with those in `path`, migrating files from older versions
+#[tauri::command]
+pub fn import_settings(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    path: PathBuf,
+) -> Result<Settings, SettingsError> {
+    let json = fs::read_to_string(&path).map_err(io_error)?;
+    let settings = from_json(&json)?;
+    store.replace(&app, settings)
+}
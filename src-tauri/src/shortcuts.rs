@@ -0,0 +1,316 @@
+use crate::capture::{open_capture, CaptureMode};
+use crate::clipper::convert_clipboard;
+use crate::inbox::AppendTarget;
+use crate::settings::{SettingsError, SettingsStore, ShortcutBindings};
+use crate::switcher;
+use crate::templates;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, State};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// Everything that can be bound to a global shortcut
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutAction {
+    NewNote,
+    MainWindow,
+    ClipToMarkdown,
+    ClipToNote,
+    AppendInbox,
+    CaptureSelection,
+    TodayNote,
+    QuickSwitcher,
+    ImageToNote,
+}
+
+impl ShortcutAction {
+    pub const ALL: [ShortcutAction; 9] = [
+        ShortcutAction::NewNote,
+        ShortcutAction::MainWindow,
+        ShortcutAction::ClipToMarkdown,
+        ShortcutAction::ClipToNote,
+        ShortcutAction::AppendInbox,
+        ShortcutAction::CaptureSelection,
+        ShortcutAction::TodayNote,
+        ShortcutAction::QuickSwitcher,
+        ShortcutAction::ImageToNote,
+    ];
+
+    /// `None` for actions that start out unbound
+    pub fn default_binding(self) -> Option<&'static str> {
+        let binding = match self {
+            ShortcutAction::NewNote => "Ctrl+Alt+N",
+            ShortcutAction::MainWindow => "Ctrl+Alt+G",
+            ShortcutAction::ClipToMarkdown => "Ctrl+Alt+V",
+            ShortcutAction::AppendInbox => "Ctrl+Alt+I",
+            ShortcutAction::CaptureSelection => "Ctrl+Alt+S",
+            ShortcutAction::TodayNote => "Ctrl+Alt+D",
+            ShortcutAction::QuickSwitcher => "Ctrl+Alt+K",
+            ShortcutAction::ClipToNote | ShortcutAction::ImageToNote => return None,
+        };
+        Some(binding)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShortcutAction::NewNote => "New note",
+            ShortcutAction::MainWindow => "Show main window",
+            ShortcutAction::ClipToMarkdown => "Clip to Markdown",
+            ShortcutAction::ClipToNote => "Clip to new note",
+            ShortcutAction::AppendInbox => "Append to inbox",
+            ShortcutAction::CaptureSelection => "Capture selection",
+            ShortcutAction::TodayNote => "Today's note",
+            ShortcutAction::QuickSwitcher => "Quick switcher",
+            ShortcutAction::ImageToNote => "Clipboard image to note",
+        }
+    }
+}
+
+/// Why a binding could not be applied
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ShortcutError {
+    /// The string isn't a shortcut, e.g. "Ctrl+Alt+"
+    Invalid { binding: String, reason: String },
+    /// Another Gravity shortcut already uses it
+    Conflict { binding: String, owner: String },
+    /// Another application has already claimed it
+    InUse { binding: String },
+    /// The OS refused for some other reason
+    Failed { binding: String, reason: String },
+    /// The settings holding the bindings could not be saved
+    Storage { reason: String },
+}
+
+impl fmt::Display for ShortcutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShortcutError::Invalid { binding, reason } => write!(f, "'{}' is not a valid shortcut: {}", binding, reason),
+            ShortcutError::Conflict { binding, owner } => write!(f, "'{}' is already used by {}", binding, owner),
+            ShortcutError::InUse { binding } => write!(f, "'{}' is already in use by another application", binding),
+            ShortcutError::Failed { binding, reason } => write!(f, "Could not register '{}': {}", binding, reason),
+            ShortcutError::Storage { reason } => write!(f, "Could not save shortcuts: {}", reason),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ShortcutInfo {
+    pub action: ShortcutAction,
+    /// `None` when the action is unbound
+    pub binding: Option<String>,
+    pub default_binding: Option<String>,
+    /// Why the binding isn't active, e.g. it failed to register at startup
+    pub error: Option<ShortcutError>,
+}
+
+struct Binding {
+    binding: Option<String>,
+    /// Set while the binding is registered with the OS
+    active: Option<Shortcut>,
+    error: Option<ShortcutError>,
+}
+
+/// The registered global shortcuts; the bindings themselves are saved with the settings
+#[derive(Default)]
+pub struct ShortcutRegistry {
+    bindings: Mutex<BTreeMap<ShortcutAction, Binding>>,
+}
+
+pub fn parse_binding(binding: &str) -> Result<Shortcut, ShortcutError> {
+    binding.parse::<Shortcut>().map_err(|e| ShortcutError::Invalid {
+        binding: binding.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Run the action bound to a shortcut
+fn trigger(app: &AppHandle, action: ShortcutAction) {
+    match action {
+        ShortcutAction::NewNote => open_capture(app, CaptureMode::NewNote),
+        ShortcutAction::MainWindow => crate::focus_main_window(app),
+        ShortcutAction::ClipToMarkdown => {
+            // Reading the clipboard may wait on its owner or a clipboard tool, keep it off the event loop
+            let app = app.clone();
+            std::thread::spawn(move || match convert_clipboard() {
+                Ok(markdown) => {
+                    log::info!("Clipboard converted to Markdown");
+                    let lines = markdown.lines().count();
+                    crate::notify(&app, "Clipboard converted to Markdown", &format!("{} lines ready to paste", lines));
+                }
+                Err(e) => {
+                    log::error!("Clip to markdown failed: {}", e);
+                    crate::notify(&app, "Clip to Markdown failed", &e);
+                }
+            });
+        }
+        ShortcutAction::ClipToNote => {
+            // Same clipboard reads as above
+            let app = app.clone();
+            std::thread::spawn(move || crate::clip_as_note(&app));
+        }
+        ShortcutAction::AppendInbox => open_capture(app, CaptureMode::Append { target: AppendTarget::Inbox }),
+        ShortcutAction::CaptureSelection => {
+            // Reading the selection may wait on the source app, keep it off the event loop
+            let app = app.clone();
+            std::thread::spawn(move || crate::capture_selection(&app));
+        }
+        ShortcutAction::TodayNote => crate::open_today_note(app),
+        ShortcutAction::QuickSwitcher => switcher::open_switcher(app),
+        ShortcutAction::ImageToNote => {
+            // Encoding a large screenshot takes a moment, keep it off the event loop
+            let app = app.clone();
+            std::thread::spawn(move || crate::paste_image_as_note(&app));
+        }
+    }
+}
+
+fn register(app: &AppHandle, action: ShortcutAction, binding: &str, shortcut: Shortcut) -> Result<(), ShortcutError> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                trigger(app, action);
+            }
+        })
+        .map_err(|e| {
+            let reason = e.to_string();
+            if reason.contains("already registered") {
+                ShortcutError::InUse { binding: binding.to_string() }
+            } else {
+                ShortcutError::Failed { binding: binding.to_string(), reason }
+            }
+        })
+}
+
+/// Name of the template whose `shortcut:` option is `shortcut`, if any
+fn template_using(app: &AppHandle, shortcut: &Shortcut) -> Option<String> {
+    templates::load_templates(app).ok()?.into_iter().find_map(|template| {
+        let binding = template.options.get("shortcut")?;
+        let parsed: Shortcut = binding.parse().ok()?;
+        (parsed.id() == shortcut.id()).then(|| template.name.clone())
+    })
+}
+
+impl ShortcutRegistry {
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<ShortcutAction, Binding>> {
+        self.bindings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register the configured bindings; failures are kept so the settings UI can show them
+    pub fn register_all(&self, app: &AppHandle, configured: &ShortcutBindings) {
+        let mut bindings = self.lock();
+
+        for action in ShortcutAction::ALL {
+            let binding = configured.get(&action).cloned().flatten();
+            let entry = bind(app, action, binding);
+            if let Some(e) = &entry.error {
+                log::error!("{} shortcut: {}", action.label(), e);
+            }
+            bindings.insert(action, entry);
+        }
+    }
+
+    fn infos(bindings: &BTreeMap<ShortcutAction, Binding>) -> Vec<ShortcutInfo> {
+        bindings
+            .iter()
+            .map(|(action, binding)| ShortcutInfo {
+                action: *action,
+                binding: binding.binding.clone(),
+                default_binding: action.default_binding().map(String::from),
+                error: binding.error.clone(),
+            })
+            .collect()
+    }
+
+    /// Re-register the actions whose binding differs between `old` and `new`.
+    /// If any fails, every changed action goes back to its old binding.
+    pub fn apply(&self, app: &AppHandle, old: &ShortcutBindings, new: &ShortcutBindings) -> Result<(), ShortcutError> {
+        let changed: Vec<ShortcutAction> = ShortcutAction::ALL
+            .into_iter()
+            .filter(|action| old.get(action) != new.get(action))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        for action in &changed {
+            let Some(binding) = new.get(action).cloned().flatten() else {
+                continue;
+            };
+            let shortcut = parse_binding(&binding)?;
+            if let Some(name) = template_using(app, &shortcut) {
+                return Err(ShortcutError::Conflict { binding, owner: format!("template '{}'", name) });
+            }
+        }
+
+        let mut bindings = self.lock();
+
+        // Release everything first, so two actions can swap bindings
+        for action in &changed {
+            if let Some(active) = bindings.get(action).and_then(|b| b.active) {
+                if let Err(e) = app.global_shortcut().unregister(active) {
+                    log::warn!("Failed to unregister {} shortcut: {}", action.label(), e);
+                }
+            }
+        }
+
+        let mut applied = Vec::new();
+        for action in &changed {
+            let entry = bind(app, *action, new.get(action).cloned().flatten());
+            if let Some(error) = entry.error {
+                for shortcut in applied.iter().filter_map(|(_, entry): &(ShortcutAction, Binding)| entry.active) {
+                    let _ = app.global_shortcut().unregister(shortcut);
+                }
+                for action in &changed {
+                    bindings.insert(*action, bind(app, *action, old.get(action).cloned().flatten()));
+                }
+                return Err(error);
+            }
+            applied.push((*action, entry));
+        }
+
+        bindings.extend(applied);
+        Ok(())
+    }
+}
+
+/// Parse and register `binding` for `action`, recording why it isn't active if that fails
+fn bind(app: &AppHandle, action: ShortcutAction, binding: Option<String>) -> Binding {
+    let Some(binding) = binding else {
+        return Binding { binding: None, active: None, error: None };
+    };
+
+    match parse_binding(&binding).and_then(|shortcut| register(app, action, &binding, shortcut).map(|_| shortcut)) {
+        Ok(shortcut) => Binding { binding: Some(binding), active: Some(shortcut), error: None },
+        Err(e) => Binding { binding: Some(binding), active: None, error: Some(e) },
+    }
+}
+
+#[tauri::command]
+pub fn get_shortcuts(registry: State<'_, ShortcutRegistry>) -> Vec<ShortcutInfo> {
+    ShortcutRegistry::infos(&registry.lock())
+}
+
+/// Bind `action` to `binding`, or unbind it when `binding` is empty or missing
+#[tauri::command]
+pub fn set_shortcut(
+    app: AppHandle,
+    registry: State<'_, ShortcutRegistry>,
+    settings: State<'_, SettingsStore>,
+    action: ShortcutAction,
+    binding: Option<String>,
+) -> Result<Vec<ShortcutInfo>, ShortcutError> {
+    settings
+        .update(&app, |settings| {
+            settings.shortcuts.insert(action, binding);
+        })
+        .map_err(|e| match e {
+            SettingsError::Shortcut { error } => error,
+            other => ShortcutError::Storage { reason: other.to_string() },
+        })?;
+
+    Ok(ShortcutRegistry::infos(&registry.lock()))
+}
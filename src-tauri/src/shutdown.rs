@@ -0,0 +1,166 @@
+use crate::capture;
+use crate::commands::remove_if_empty;
+use crate::storage::get_notes_dir;
+use crate::switcher::SWITCHER_LABEL;
+use crate::windows;
+use crate::writer::WriteQueue;
+use std::collections::HashSet;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+/// Sent to every window when the app starts shutting down
+pub const SHUTDOWN_EVENT: &str = "app-shutdown";
+
+/// How long windows get to flush their pending saves
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long to wait for saves already in progress
+const SAVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Default, PartialEq)]
+enum Phase {
+    #[default]
+    Running,
+    ShuttingDown,
+    Done,
+}
+
+#[derive(Default)]
+struct Inner {
+    phase: Phase,
+    /// Windows that haven't confirmed their flush yet
+    waiting: HashSet<String>,
+    /// `save_note` calls in progress
+    saves: usize,
+}
+
+/// Coordinates the shutdown sequence with open windows and in-flight saves
+#[derive(Default)]
+pub struct Shutdown {
+    inner: Mutex<Inner>,
+    changed: Condvar,
+}
+
+/// Counts a save as in progress until dropped
+pub struct SaveGuard<'a>(&'a Shutdown);
+
+impl Drop for SaveGuard<'_> {
+    fn drop(&mut self) {
+        self.0.lock().saves -= 1;
+        self.0.changed.notify_all();
+    }
+}
+
+impl Shutdown {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn track_save(&self) -> SaveGuard<'_> {
+        self.lock().saves += 1;
+        SaveGuard(self)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.lock().phase == Phase::Done
+    }
+
+    /// Block until `done` holds or `timeout` passes; returns whether it holds
+    fn wait_until(&self, timeout: Duration, done: impl Fn(&Inner) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut inner = self.lock();
+        while !done(&inner) {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                return false;
+            };
+            inner = self.changed.wait_timeout(inner, left).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        true
+    }
+}
+
+/// The last steps, safe to run without the windows: drop empty capture notes and
+/// remember the window session
+fn finish(app: &AppHandle) {
+    let notes_dir = get_notes_dir(app);
+    let queue = app.state::<WriteQueue>();
+    for id in capture::open_notes(app) {
+        match queue.delete(&id, || remove_if_empty(&notes_dir, &id)) {
+            Ok(true) => log::info!("Removed empty capture note {}", id),
+            Ok(false) => {}
+            Err(e) => log::error!("Failed to clean up capture note {}: {}", id, e),
+        }
+    }
+
+    windows::save_session(app);
+}
+
+/// Start the shutdown sequence for an exit with `code`, then exit once it completes
+pub fn request(app: &AppHandle, code: i32) {
+    let shutdown = app.state::<Shutdown>();
+    {
+        let mut inner = shutdown.lock();
+        if inner.phase != Phase::Running {
+            return;
+        }
+        inner.phase = Phase::ShuttingDown;
+        inner.waiting = app
+            .webview_windows()
+            .into_keys()
+            .filter(|label| label != SWITCHER_LABEL)
+            .collect();
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let shutdown = app.state::<Shutdown>();
+
+        if let Err(e) = app.emit(SHUTDOWN_EVENT, ()) {
+            log::error!("Failed to notify windows of shutdown: {}", e);
+        }
+        if !shutdown.wait_until(FLUSH_TIMEOUT, |inner| inner.waiting.is_empty()) {
+            log::warn!("Windows did not flush before shutdown: {:?}", shutdown.lock().waiting);
+        }
+        if !shutdown.wait_until(SAVE_TIMEOUT, |inner| inner.saves == 0) {
+            log::warn!("Saves still in progress at shutdown");
+        }
+        if !app.state::<WriteQueue>().flush(SAVE_TIMEOUT) {
+            log::warn!("Queued note writes did not finish before shutdown");
+        }
+
+        finish(&app);
+        shutdown.lock().phase = Phase::Done;
+
+        if code == tauri::RESTART_EXIT_CODE {
+            app.restart();
+        } else {
+            app.exit(code);
+        }
+    });
+}
+
+/// The process is exiting without the sequence having run, e.g. the OS is ending the session
+pub fn on_exit(app: &AppHandle) {
+    let shutdown = app.state::<Shutdown>();
+    if shutdown.is_done() {
+        return;
+    }
+    shutdown.lock().phase = Phase::Done;
+    app.state::<WriteQueue>().flush(SAVE_TIMEOUT);
+    finish(app);
+}
+
+/// A window closed while shutting down has nothing left to flush
+pub fn forget_window(app: &AppHandle, label: &str) {
+    let shutdown = app.state::<Shutdown>();
+    shutdown.lock().waiting.remove(label);
+    shutdown.changed.notify_all();
+}
+
+/// Called by a window once it has saved everything after `app-shutdown`
+#[tauri::command]
+pub fn shutdown_flushed(window: WebviewWindow, shutdown: State<'_, Shutdown>) {
+    shutdown.lock().waiting.remove(window.label());
+    shutdown.changed.notify_all();
+}
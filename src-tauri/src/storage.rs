@@ -14,6 +14,17 @@ pub struct NoteMeta {
     pub modified_at: String,
     pub word_count: usize,
     pub char_count: usize,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub last_opened: Option<String>,
+    /// First image the note refers to, for the card view's thumbnail
+    #[serde(default)]
+    pub image: Option<String>,
 }
 
 pub fn get_notes_dir(app: &AppHandle) -> PathBuf {
@@ -85,7 +96,62 @@ pub fn ensure_images_dir(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(images_dir)
 }
 
+/// Files attached to notes, stored under their original names
+pub fn get_attachments_dir(app: &AppHandle) -> PathBuf {
+    let app_data = app.path().app_data_dir().expect("Failed to get app data dir");
+    app_data.join("attachments")
+}
+
+pub fn ensure_attachments_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let attachments_dir = get_attachments_dir(app);
+    if !attachments_dir.exists() {
+        fs::create_dir_all(&attachments_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(attachments_dir)
+}
+
+/// Small previews of the stored images, named after the image with a `.png` suffix
+pub fn get_thumbnails_dir(app: &AppHandle) -> PathBuf {
+    let app_data = app.path().app_data_dir().expect("Failed to get app data dir");
+    app_data.join("thumbnails")
+}
+
+pub fn ensure_thumbnails_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let thumbnails_dir = get_thumbnails_dir(app);
+    if !thumbnails_dir.exists() {
+        fs::create_dir_all(&thumbnails_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(thumbnails_dir)
+}
+
+/// Deleted notes and removed images wait here until the trash is purged
+pub fn get_trash_dir(app: &AppHandle) -> PathBuf {
+    let app_data = app.path().app_data_dir().expect("Failed to get app data dir");
+    app_data.join("trash")
+}
+
+pub fn ensure_trash_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let trash_dir = get_trash_dir(app);
+    if !trash_dir.exists() {
+        fs::create_dir_all(&trash_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(trash_dir)
+}
+
 pub fn generate_image_filename(extension: &str) -> String {
     let now = Local::now();
     now.format(&format!("%Y-%m-%d_%H-%M-%S-%3f.{}", extension)).to_string()
 }
+
+pub fn get_templates_dir(app: &AppHandle) -> PathBuf {
+    let app_data = app.path().app_data_dir().expect("Failed to get app data dir");
+    app_data.join("templates")
+}
+
+pub fn ensure_templates_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let templates_dir = get_templates_dir(app);
+    if !templates_dir.exists() {
+        fs::create_dir_all(&templates_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(templates_dir)
+}
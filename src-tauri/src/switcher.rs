@@ -0,0 +1,321 @@
+use crate::commands::{apply_state, read_meta_without_state, sort_notes, write_new_note};
+use crate::note_state;
+use crate::storage::{ensure_notes_dir, NoteMeta};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewUrl, WebviewWindowBuilder};
+
+pub const SWITCHER_LABEL: &str = "switcher";
+
+const DEFAULT_LIMIT: usize = 20;
+
+/// Longest snippet shown under a content match, in characters
+const SNIPPET_CHARS: usize = 90;
+
+/// A note as the matcher sees it, re-read only when the file changes. Pinning and the other
+/// flags don't touch the file, so `meta` has them applied at search time.
+struct IndexedNote {
+    meta: NoteMeta,
+    modified: SystemTime,
+    title: String,
+    title_chars: Vec<char>,
+    content: String,
+    content_lower: String,
+}
+
+/// In-memory search index over the notes folder, keyed by note ID
+#[derive(Default)]
+pub struct SearchIndex {
+    notes: Mutex<HashMap<String, IndexedNote>>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SwitcherMatch {
+    pub note: NoteMeta,
+    pub score: i64,
+    /// Character positions in the title that matched, for highlighting
+    pub title_positions: Vec<usize>,
+    /// Line of content containing the first query term, when it matched there
+    pub snippet: Option<String>,
+}
+
+impl SearchIndex {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, IndexedNote>> {
+        self.notes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Bring the index up to date, reading only notes whose mtime changed
+    fn refresh(&self, notes_dir: &Path) -> Result<(), String> {
+        let mut notes = self.lock();
+        let mut seen = HashSet::new();
+
+        for entry in fs::read_dir(notes_dir).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+                continue;
+            }
+            let Some(id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+                continue;
+            };
+            seen.insert(id.clone());
+
+            if notes.get(&id).is_some_and(|note| note.modified == modified) {
+                continue;
+            }
+
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let meta = read_meta_without_state(&path, &content)?;
+            notes.insert(
+                id,
+                IndexedNote {
+                    title: meta.title.to_lowercase(),
+                    title_chars: meta.title.to_lowercase().chars().collect(),
+                    content_lower: content.to_lowercase(),
+                    content,
+                    meta,
+                    modified,
+                },
+            );
+        }
+
+        notes.retain(|id, _| seen.contains(id));
+        Ok(())
+    }
+
+    /// Every note, archived ones included, with its state applied and sorted like the note
+    /// list. Only notes whose file changed since the last call are read.
+    pub fn list(&self, notes_dir: &Path) -> Result<Vec<NoteMeta>, String> {
+        self.refresh(notes_dir)?;
+        let states = note_state::load_states(notes_dir);
+
+        let mut notes: Vec<NoteMeta> = self
+            .lock()
+            .values()
+            .map(|note| {
+                let mut meta = note.meta.clone();
+                if let Some(state) = states.get(&meta.id) {
+                    apply_state(&mut meta, state);
+                }
+                meta
+            })
+            .collect();
+        sort_notes(&mut notes, &states);
+        Ok(notes)
+    }
+}
+
+fn is_word_start(text: &[char], index: usize) -> bool {
+    index == 0 || !text[index - 1].is_alphanumeric()
+}
+
+/// Score `pattern` as a subsequence of `text`, favouring consecutive runs and word starts.
+/// Both are expected lowercase. Returns the score and the matched positions.
+fn fuzzy_score(pattern: &[char], text: &[char]) -> Option<(i64, Vec<usize>)> {
+    if pattern.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    // Jumping ahead to a word start can strand later characters; fall back to plain order
+    match_subsequence(pattern, text, true).or_else(|| match_subsequence(pattern, text, false))
+}
+
+fn match_subsequence(pattern: &[char], text: &[char], prefer_word_starts: bool) -> Option<(i64, Vec<usize>)> {
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut score = 0i64;
+    let mut next = 0;
+
+    for &wanted in pattern {
+        // Prefer the next word start over the next plain occurrence
+        let plain = (next..text.len()).find(|&i| text[i] == wanted)?;
+        let word_start = (plain..text.len()).find(|&i| text[i] == wanted && is_word_start(text, i));
+        let consecutive = positions.last().is_some_and(|&last| last + 1 == plain);
+        let index = match word_start {
+            Some(i) if prefer_word_starts && !consecutive => i,
+            _ => plain,
+        };
+
+        score += 10;
+        if positions.last().is_some_and(|&last| last + 1 == index) {
+            score += 15;
+        }
+        if is_word_start(text, index) {
+            score += 20;
+        }
+        score -= (index - next).min(10) as i64;
+
+        positions.push(index);
+        next = index + 1;
+    }
+
+    if positions.first() == Some(&0) {
+        score += 15;
+    }
+    // Tighter matches in shorter titles rank higher
+    score -= (text.len() as i64 - pattern.len() as i64).clamp(0, 20) / 4;
+
+    Some((score, positions))
+}
+
+/// The content line holding `term`, trimmed to a short excerpt around it
+fn snippet_for(content: &str, term: &str) -> Option<String> {
+    let line = content.lines().find(|line| line.to_lowercase().contains(term))?;
+    let line = line.trim().trim_start_matches('#').trim();
+    let chars: Vec<char> = line.chars().collect();
+    if chars.len() <= SNIPPET_CHARS {
+        return Some(line.to_string());
+    }
+
+    let lower: Vec<char> = line.to_lowercase().chars().collect();
+    let term: Vec<char> = term.chars().collect();
+    let hit = (0..lower.len())
+        .find(|&i| lower[i..].starts_with(&term))
+        .unwrap_or(0)
+        .min(chars.len());
+    let start = hit.saturating_sub(SNIPPET_CHARS / 3);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+
+    let mut snippet: String = chars[start..end].iter().collect();
+    if start > 0 {
+        snippet = format!("...{}", snippet);
+    }
+    if end < chars.len() {
+        snippet.push_str("...");
+    }
+    Some(snippet)
+}
+
+fn score_note(note: &IndexedNote, query: &str, terms: &[&str]) -> Option<SwitcherMatch> {
+    let pattern: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    let title_match = fuzzy_score(&pattern, &note.title_chars);
+
+    // Every term has to appear somewhere for a content match
+    let in_content = terms
+        .iter()
+        .all(|term| note.content_lower.contains(term) || note.title.contains(term));
+
+    let (mut score, title_positions) = match title_match {
+        Some((score, positions)) => (score * 3, positions),
+        None if in_content => (0, Vec::new()),
+        None => return None,
+    };
+
+    let mut snippet = None;
+    if in_content {
+        score += 5 * terms.len() as i64;
+        if title_positions.is_empty() {
+            snippet = terms.first().and_then(|term| snippet_for(&note.content, term));
+        }
+    }
+
+    Some(SwitcherMatch {
+        note: note.meta.clone(),
+        score,
+        title_positions,
+        snippet,
+    })
+}
+
+/// Rank notes against `query`; an empty query lists the most recently modified notes
+pub fn search(app: &AppHandle, index: &SearchIndex, query: &str, limit: usize) -> Result<Vec<SwitcherMatch>, String> {
+    let notes_dir = ensure_notes_dir(app)?;
+    index.refresh(&notes_dir)?;
+
+    let states = note_state::load_states(&notes_dir);
+
+    let query = query.trim().to_lowercase();
+    let terms: Vec<&str> = query.split_whitespace().collect();
+
+    let notes = index.lock();
+    let mut matches: Vec<SwitcherMatch> = notes
+        .values()
+        .filter(|note| !states.get(&note.meta.id).is_some_and(|state| state.archived))
+        .filter_map(|note| {
+            if query.is_empty() {
+                Some(SwitcherMatch {
+                    note: note.meta.clone(),
+                    score: 0,
+                    title_positions: Vec::new(),
+                    snippet: None,
+                })
+            } else {
+                score_note(note, &query, &terms)
+            }
+        })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b.note.modified_at.cmp(&a.note.modified_at))
+    });
+    matches.truncate(limit);
+    for found in matches.iter_mut() {
+        if let Some(state) = states.get(&found.note.id) {
+            apply_state(&mut found.note, state);
+        }
+    }
+
+    Ok(matches)
+}
+
+/// Show the quick switcher, building it on first use
+pub fn open_switcher(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(SWITCHER_LABEL) {
+        let _ = app.emit_to(SWITCHER_LABEL, "switcher-activate", ());
+        let _ = window.center();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+
+    let url = WebviewUrl::App("index.html?window=switcher".into());
+    let builder = WebviewWindowBuilder::new(app, SWITCHER_LABEL, url)
+        .title("Quick Switcher")
+        .inner_size(560.0, 380.0)
+        .center()
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .focused(true);
+
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    let builder = builder.transparent(true).shadow(false);
+
+    if let Err(e) = builder.build() {
+        log::error!("Failed to create quick switcher: {}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn search_notes(
+    app: AppHandle,
+    index: State<'_, SearchIndex>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SwitcherMatch>, String> {
+    search(&app, &index, &query, limit.unwrap_or(DEFAULT_LIMIT))
+}
+
+/// Create a note holding `text`, used when the switcher finds nothing to open
+#[tauri::command]
+pub async fn create_note_with_text(app: AppHandle, text: String) -> Result<NoteMeta, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Note text is empty".to_string());
+    }
+
+    let note = write_new_note(&app, &format!("{}\n", text))?;
+    let _ = app.emit("notes-changed", ());
+    Ok(note)
+}
@@ -0,0 +1,286 @@
+use crate::commands::write_new_note;
+use crate::storage::{ensure_templates_dir, NoteMeta};
+use arboard::Clipboard;
+use chrono::{DateTime, Days, Local, Months, NaiveDate};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TemplateMeta {
+    pub name: String,
+    pub shortcut: Option<String>,
+}
+
+/// A note created from a template, with the caret position requested by `{{cursor}}`
+#[derive(Debug, Serialize, Clone)]
+pub struct TemplateNote {
+    pub note: NoteMeta,
+    pub content: String,
+    /// Cursor offset in UTF-16 code units, ready for the editor
+    pub cursor: Option<usize>,
+}
+
+/// A template file split into its frontmatter options and body
+pub struct Template {
+    pub name: String,
+    pub options: HashMap<String, String>,
+    pub body: String,
+}
+
+/// Parse a template file; an optional leading `---` block holds `key: value` options
+pub fn parse_template(name: &str, raw: &str) -> Template {
+    let mut options = HashMap::new();
+    let mut body = raw;
+
+    let normalized = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+    if let Some(rest) = normalized
+        .strip_prefix("---\n")
+        .or_else(|| normalized.strip_prefix("---\r\n"))
+    {
+        if let Some(end) = rest.find("\n---") {
+            for line in rest[..end].lines() {
+                if let Some((key, value)) = line.split_once(':') {
+                    options.insert(key.trim().to_lowercase(), value.trim().to_string());
+                }
+            }
+            body = rest[end + 4..].trim_start_matches(['\r', '\n']);
+        }
+    }
+
+    Template {
+        name: name.to_string(),
+        options,
+        body: body.to_string(),
+    }
+}
+
+fn template_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid template name: {}", name));
+    }
+    Ok(ensure_templates_dir(app)?.join(format!("{}.md", name)))
+}
+
+pub fn load_template(app: &AppHandle, name: &str) -> Result<Template, String> {
+    let path = template_path(app, name)?;
+    let raw = fs::read_to_string(&path).map_err(|e| format!("Template '{}': {}", name, e))?;
+    Ok(parse_template(name, &raw))
+}
+
+/// Every template in the templates directory, sorted by name
+pub fn load_templates(app: &AppHandle) -> Result<Vec<Template>, String> {
+    let templates_dir = ensure_templates_dir(app)?;
+    let mut templates = Vec::new();
+
+    let entries = fs::read_dir(&templates_dir).map_err(|e| e.to_string())?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "md") {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            if let Ok(raw) = fs::read_to_string(&path) {
+                templates.push(parse_template(&name, &raw));
+            }
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// Parse date arithmetic like `+7d`, `-1w`, `+2m` or `+1y` and apply it to `date`
+fn shift_date(date: NaiveDate, offset: &str) -> Option<NaiveDate> {
+    let (sign, rest) = match offset.chars().next()? {
+        '+' => (1, &offset[1..]),
+        '-' => (-1, &offset[1..]),
+        _ => return None,
+    };
+    let unit = rest.chars().last()?;
+    let amount: u32 = rest[..rest.len() - unit.len_utf8()].parse().ok()?;
+
+    match (unit, sign) {
+        ('d', 1) => date.checked_add_days(Days::new(amount as u64)),
+        ('d', _) => date.checked_sub_days(Days::new(amount as u64)),
+        ('w', 1) => date.checked_add_days(Days::new(amount as u64 * 7)),
+        ('w', _) => date.checked_sub_days(Days::new(amount as u64 * 7)),
+        ('m', 1) => date.checked_add_months(Months::new(amount)),
+        ('m', _) => date.checked_sub_months(Months::new(amount)),
+        ('y', 1) => date.checked_add_months(Months::new(amount.checked_mul(12)?)),
+        ('y', _) => date.checked_sub_months(Months::new(amount.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+/// Value for a single `{{variable}}`, or `None` to leave it untouched
+fn resolve_variable(name: &str, now: &DateTime<Local>, clipboard: &mut Option<String>) -> Option<String> {
+    match name {
+        "date" => Some(now.format("%Y-%m-%d").to_string()),
+        "time" => Some(now.format("%H:%M").to_string()),
+        "datetime" => Some(now.format("%Y-%m-%d %H:%M").to_string()),
+        "weekday" => Some(now.format("%A").to_string()),
+        "clipboard" => Some(
+            clipboard
+                .get_or_insert_with(|| {
+                    Clipboard::new()
+                        .and_then(|mut c| c.get_text())
+                        .unwrap_or_default()
+                })
+                .clone(),
+        ),
+        _ => {
+            let offset = name.strip_prefix("date")?;
+            shift_date(now.date_naive(), offset).map(|d| d.format("%Y-%m-%d").to_string())
+        }
+    }
+}
+
+/// Expand template variables as of `now`; returns the text and the byte offset of `{{cursor}}`
+pub fn expand(body: &str, now: DateTime<Local>) -> (String, Option<usize>) {
+    let mut output = String::with_capacity(body.len());
+    let mut cursor = None;
+    let mut clipboard = None;
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let name = after[..end].trim();
+        if name == "cursor" {
+            cursor.get_or_insert(output.len());
+        } else if let Some(value) = resolve_variable(name, &now, &mut clipboard) {
+            output.push_str(&value);
+        } else {
+            output.push_str(&rest[start..start + 2 + end + 2]);
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+
+    (output, cursor)
+}
+
+/// Create a new note from a template and return it along with the caret position
+pub fn create_from_template(app: &AppHandle, name: &str) -> Result<TemplateNote, String> {
+    let template = load_template(app, name)?;
+    let (content, cursor) = expand(&template.body, Local::now());
+    let note = write_new_note(app, &content)?;
+
+    Ok(TemplateNote {
+        note,
+        cursor: cursor.map(|byte| content[..byte].encode_utf16().count()),
+        content,
+    })
+}
+
+#[tauri::command]
+pub async fn list_templates(app: AppHandle) -> Result<Vec<TemplateMeta>, String> {
+    Ok(load_templates(&app)?
+        .into_iter()
+        .map(|t| TemplateMeta {
+            shortcut: t.options.get("shortcut").cloned(),
+            name: t.name,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn create_note_from_template(app: AppHandle, name: String) -> Result<TemplateNote, String> {
+    create_from_template(&app, &name)
+}
+
+#[tauri::command]
+pub async fn get_templates_path(app: AppHandle) -> Result<String, String> {
+    let templates_dir = ensure_templates_dir(&app)?;
+    Ok(templates_dir.to_string_lossy().to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Saturday 2026-01-31 09:05, the last day of a month
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 1, 31, 9, 5, 0).unwrap()
+    }
+
+    fn expand_text(body: &str) -> String {
+        expand(body, now()).0
+    }
+
+    #[test]
+    fn expands_variables() {
+        assert_eq!(
+            expand_text("# {{date}} ({{weekday}})\n{{ time }} · {{datetime}}"),
+            "# 2026-01-31 (Saturday)\n09:05 · 2026-01-31 09:05"
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_variables() {
+        assert_eq!(expand_text("{{title}} and {{date+}} and {{}}"), "{{title}} and {{date+}} and {{}}");
+    }
+
+    #[test]
+    fn date_arithmetic() {
+        assert_eq!(expand_text("{{date+1d}} {{date-1d}}"), "2026-02-01 2026-01-30");
+        assert_eq!(expand_text("{{date+2w}} {{date-1w}}"), "2026-02-14 2026-01-24");
+        // Months clamp to the last day of a shorter month
+        assert_eq!(expand_text("{{date+1m}} {{date-2m}}"), "2026-02-28 2025-11-30");
+        assert_eq!(expand_text("{{date+1y}} {{date-10y}}"), "2027-01-31 2016-01-31");
+    }
+
+    #[test]
+    fn rejects_malformed_offsets() {
+        assert_eq!(shift_date(now().date_naive(), "7d"), None);
+        assert_eq!(shift_date(now().date_naive(), "+7x"), None);
+        assert_eq!(shift_date(now().date_naive(), "+d"), None);
+        assert_eq!(shift_date(now().date_naive(), "+-1d"), None);
+    }
+
+    #[test]
+    fn year_offsets_that_overflow_are_left_alone() {
+        // 400000000 years is more months than fit in a u32
+        assert_eq!(shift_date(now().date_naive(), "+400000000y"), None);
+        assert_eq!(expand_text("{{date+400000000y}}"), "{{date+400000000y}}");
+        // Fits in months but not in a date
+        assert_eq!(expand_text("{{date-4000000m}}"), "{{date-4000000m}}");
+    }
+
+    #[test]
+    fn cursor_position() {
+        let (text, cursor) = expand("# {{date}}\n\n{{cursor}}done{{cursor}}", now());
+        assert_eq!(text, "# 2026-01-31\n\ndone");
+        // The first marker wins, counted in bytes of the expanded text
+        assert_eq!(cursor, Some("# 2026-01-31\n\n".len()));
+        assert_eq!(expand("no marker", now()).1, None);
+    }
+
+    #[test]
+    fn unterminated_braces_are_kept() {
+        assert_eq!(expand_text("{{date}} then {{date"), "2026-01-31 then {{date");
+        assert_eq!(expand_text("{{ cursor"), "{{ cursor");
+        assert_eq!(expand("{{ cursor", now()).1, None);
+    }
+
+    #[test]
+    fn parses_frontmatter_options() {
+        let template = parse_template("daily", "\u{feff}---\r\nShortcut: Ctrl+D\r\nnote: a: b\r\n---\r\n\r\n# {{date}}\n");
+        assert_eq!(template.options.get("shortcut").map(String::as_str), Some("Ctrl+D"));
+        assert_eq!(template.options.get("note").map(String::as_str), Some("a: b"));
+        assert_eq!(template.body, "# {{date}}\n");
+
+        let plain = parse_template("plain", "---\nno closing fence");
+        assert!(plain.options.is_empty());
+        assert_eq!(plain.body, "---\nno closing fence");
+    }
+}
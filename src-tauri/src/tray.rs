@@ -0,0 +1,166 @@
+use crate::clipper;
+use crate::commands::write_new_note;
+use crate::storage::{ensure_notes_dir, NoteMeta};
+use crate::switcher::SearchIndex;
+use crate::windows;
+use chrono::{DateTime, FixedOffset};
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const TRAY_ID: &str = "main";
+
+/// How many notes the "Recent" submenu lists
+const RECENT_LIMIT: usize = 8;
+
+/// Notes change on every throttled save; collapse bursts into one rebuild
+const REFRESH_DELAY: Duration = Duration::from_secs(1);
+
+const OPEN_NOTE_PREFIX: &str = "open-note:";
+
+static REFRESH_PENDING: AtomicBool = AtomicBool::new(false);
+
+fn menu_title(note: &NoteMeta) -> String {
+    let title = if note.title.is_empty() { "Untitled" } else { note.title.as_str() };
+    if title.chars().count() > 40 {
+        format!("{}...", title.chars().take(37).collect::<String>())
+    } else {
+        title.to_string()
+    }
+}
+
+/// Most recent of the last open and last modification times. They're compared as instants,
+/// since `modified_at` is UTC when read from disk but local time after a save.
+fn recency(note: &NoteMeta) -> Option<DateTime<FixedOffset>> {
+    let parse = |time: &str| DateTime::parse_from_rfc3339(time).ok();
+    let modified = parse(&note.modified_at);
+    let opened = note.last_opened.as_deref().and_then(parse);
+    modified.max(opened)
+}
+
+fn note_submenu(app: &AppHandle, title: &str, notes: &[&NoteMeta]) -> tauri::Result<Submenu<tauri::Wry>> {
+    let submenu = Submenu::new(app, title, !notes.is_empty())?;
+    for note in notes {
+        let id = format!("{}{}", OPEN_NOTE_PREFIX, note.id);
+        submenu.append(&MenuItem::with_id(app, id, menu_title(note), true, None::<&str>)?)?;
+    }
+    Ok(submenu)
+}
+
+/// All notes, archived ones included, for one menu and tooltip rebuild. They come from the
+/// search index, so a rebuild after a save reads only the saved note.
+pub fn list_notes(app: &AppHandle) -> Result<Vec<NoteMeta>, String> {
+    ensure_notes_dir(app)
+        .and_then(|notes_dir| app.state::<SearchIndex>().list(&notes_dir))
+        .inspect_err(|e| log::error!("Failed to list notes for tray: {}", e))
+}
+
+/// Build the tray menu from the listed notes
+pub fn build_menu(app: &AppHandle, notes: &[NoteMeta]) -> tauri::Result<Menu<tauri::Wry>> {
+    let unarchived = notes.iter().filter(|n| !n.archived);
+    let pinned: Vec<&NoteMeta> = unarchived.clone().filter(|n| n.pinned).collect();
+    let mut recent: Vec<&NoteMeta> = unarchived.collect();
+    recent.sort_by_cached_key(|note| Reverse(recency(note)));
+    recent.truncate(RECENT_LIMIT);
+
+    let show = MenuItem::with_id(app, "show", "Show Gravity", true, None::<&str>)?;
+    let new_note = MenuItem::with_id(app, "new_note", "New Note", true, None::<&str>)?;
+    let today = MenuItem::with_id(app, "today", "Today's Note", true, None::<&str>)?;
+    let search = MenuItem::with_id(app, "search", "Search Notes...", true, None::<&str>)?;
+    let paste = MenuItem::with_id(app, "paste_clipboard", "Paste Clipboard as Note", true, None::<&str>)?;
+    let recent_menu = note_submenu(app, "Recent", &recent)?;
+    let pinned_menu = note_submenu(app, "Pinned", &pinned)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+
+    Menu::with_items(
+        app,
+        &[
+            &show,
+            &new_note,
+            &today,
+            &search,
+            &paste,
+            &PredefinedMenuItem::separator(app)?,
+            &recent_menu,
+            &pinned_menu,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )
+}
+
+pub fn tooltip(notes: &Result<Vec<NoteMeta>, String>) -> String {
+    match notes {
+        Ok(notes) if notes.len() == 1 => "Gravity - 1 note".to_string(),
+        Ok(notes) => format!("Gravity - {} notes", notes.len()),
+        Err(_) => "Gravity".to_string(),
+    }
+}
+
+/// Rebuild the tray menu and tooltip now
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    let notes = list_notes(app);
+    match build_menu(app, notes.as_deref().unwrap_or_default()) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => log::error!("Failed to rebuild tray menu: {}", e),
+    }
+    let _ = tray.set_tooltip(Some(tooltip(&notes)));
+}
+
+/// Rebuild the tray menu shortly, coalescing bursts of changes
+pub fn schedule_refresh(app: &AppHandle) {
+    if REFRESH_PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(REFRESH_DELAY);
+        REFRESH_PENDING.store(false, Ordering::SeqCst);
+        refresh(&app);
+    });
+}
+
+fn paste_clipboard_as_note(app: &AppHandle) {
+    let Some(text) = clipper::get_clipboard_text() else {
+        log::info!("Clipboard has no text to paste as a note");
+        return;
+    };
+
+    match write_new_note(app, &text) {
+        Ok(note) => {
+            let _ = app.emit("notes-changed", ());
+            if let Err(e) = windows::open_note_window(app, &note.id, false) {
+                log::error!("Failed to open pasted note: {}", e);
+            }
+        }
+        Err(e) => log::error!("Failed to paste clipboard as note: {}", e),
+    }
+}
+
+/// Handle the dynamic tray entries; returns false for IDs it doesn't own
+pub fn handle_menu_event(app: &AppHandle, event: &MenuEvent) -> bool {
+    let id = event.id.as_ref();
+
+    if let Some(note_id) = id.strip_prefix(OPEN_NOTE_PREFIX) {
+        if let Err(e) = windows::open_note_window(app, note_id, false) {
+            log::error!("Failed to open note from tray: {}", e);
+        }
+        return true;
+    }
+
+    if id == "paste_clipboard" {
+        paste_clipboard_as_note(app);
+        return true;
+    }
+
+    false
+}
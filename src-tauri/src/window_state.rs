@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Manager};
+
+const STATE_FILENAME: &str = "window-state.json";
+
+/// Placement of a note window, in logical pixels
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NoteWindowState {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    /// Sticky notes stay on top and have no taskbar entry
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    /// Name of the monitor the window was last on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<String>,
+}
+
+/// Placement of the main window, in logical pixels
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MainWindowState {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<String>,
+}
+
+pub fn default_opacity() -> f64 {
+    0.95
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct StoredState {
+    #[serde(default)]
+    notes: HashMap<String, NoteWindowState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    main: Option<MainWindowState>,
+    /// Note windows that were open when the app last exited
+    #[serde(default)]
+    session: Vec<String>,
+}
+
+/// Remembered window placement, kept in memory and written to `window-state.json`
+#[derive(Default)]
+pub struct WindowStates {
+    state: Mutex<StoredState>,
+    /// Open note windows, label -> note ID
+    open: Mutex<HashMap<String, String>>,
+}
+
+fn state_path(app: &AppHandle) -> PathBuf {
+    let app_data = app.path().app_data_dir().expect("Failed to get app data dir");
+    app_data.join(STATE_FILENAME)
+}
+
+impl WindowStates {
+    pub fn load(app: &AppHandle) -> Self {
+        let state = fs::read_to_string(state_path(app))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        WindowStates {
+            state: Mutex::new(state),
+            open: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, StoredState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn open_windows(&self) -> MutexGuard<'_, HashMap<String, String>> {
+        self.open.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn save(&self, app: &AppHandle) -> Result<(), String> {
+        // Holding the lock through the write keeps saves in order and off each other's temp file
+        let state = self.lock();
+        let json = serde_json::to_string_pretty(&*state).map_err(|e| e.to_string())?;
+        let path = state_path(app);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        // Write to a temp file first so a crash never leaves a truncated file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    pub fn note(&self, id: &str) -> Option<NoteWindowState> {
+        self.lock().notes.get(id).cloned()
+    }
+
+    pub fn set_note(&self, id: &str, state: NoteWindowState) {
+        self.lock().notes.insert(id.to_string(), state);
+    }
+
+    /// Update the remembered state of an open note window, if it is tracked
+    pub fn update_note<F>(&self, label: &str, change: F)
+    where
+        F: FnOnce(&mut NoteWindowState),
+    {
+        let Some(id) = self.open_windows().get(label).cloned() else {
+            return;
+        };
+        if let Some(state) = self.lock().notes.get_mut(&id) {
+            change(state);
+        }
+    }
+
+    pub fn main(&self) -> Option<MainWindowState> {
+        self.lock().main.clone()
+    }
+
+    pub fn update_main<F>(&self, default: MainWindowState, change: F)
+    where
+        F: FnOnce(&mut MainWindowState),
+    {
+        change(self.lock().main.get_or_insert(default));
+    }
+
+    pub fn session(&self) -> Vec<String> {
+        self.lock().session.clone()
+    }
+
+    /// Remember the note windows open right now as the session to restore
+    pub fn capture_session(&self) {
+        let mut ids: Vec<String> = self.open_windows().values().cloned().collect();
+        ids.sort();
+        ids.dedup();
+        self.lock().session = ids;
+    }
+
+    /// Forget placement and session entries for notes not in `present`; returns the IDs dropped
+    pub fn compact(&self, present: &HashSet<String>, dry_run: bool) -> Vec<String> {
+        let mut state = self.lock();
+        let mut stale: Vec<String> = state
+            .notes
+            .keys()
+            .chain(state.session.iter())
+            .filter(|id| !present.contains(*id))
+            .cloned()
+            .collect();
+        stale.sort();
+        stale.dedup();
+
+        if !dry_run {
+            state.notes.retain(|id, _| present.contains(id));
+            state.session.retain(|id| present.contains(id));
+        }
+        stale
+    }
+
+    pub fn track(&self, label: &str, id: &str) {
+        self.open_windows().insert(label.to_string(), id.to_string());
+    }
+
+    pub fn untrack(&self, label: &str) -> Option<String> {
+        self.open_windows().remove(label)
+    }
+}
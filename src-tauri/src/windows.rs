@@ -0,0 +1,283 @@
+use crate::note_state;
+use crate::storage::{extract_title, get_notes_dir};
+use crate::tray;
+use crate::window_state::{default_opacity, MainWindowState, NoteWindowState, WindowStates};
+use std::fs;
+use tauri::{
+    AppHandle, LogicalPosition, LogicalSize, Manager, State, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder, Window, WindowEvent,
+};
+
+/// Percent-encode a value for use in a window URL query string
+pub fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// One window per note: the label is the note ID in hex, since labels allow only a few
+/// characters and replacing the others would give two IDs the same window
+pub fn note_window_label(id: &str) -> String {
+    let hex: String = id.bytes().map(|b| format!("{:02x}", b)).collect();
+    format!("note-{}", hex)
+}
+
+/// Open a note in its own window, or focus the window if it is already open
+pub fn open_note_window(app: &AppHandle, id: &str, sticky: bool) -> Result<WebviewWindow, String> {
+    let label = note_window_label(id);
+
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(window);
+    }
+
+    let path = get_notes_dir(app).join(format!("{}.md", id));
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+
+    let states = app.state::<WindowStates>();
+    let (width, height) = if sticky { (320.0, 300.0) } else { (700.0, 550.0) };
+    let state = states.note(id).unwrap_or(NoteWindowState {
+        x: 0.0,
+        y: 0.0,
+        width,
+        height,
+        pinned: sticky,
+        opacity: default_opacity(),
+        monitor: None,
+    });
+    let remembered = states.note(id).is_some()
+        && is_on_screen(app, state.x, state.y, state.width, state.monitor.as_deref());
+    let pinned = sticky || state.pinned;
+
+    let url = format!(
+        "index.html?window=note&id={}&sticky={}&opacity={}",
+        encode_query_value(id),
+        pinned,
+        state.opacity
+    );
+
+    let builder = WebviewWindowBuilder::new(app, &label, WebviewUrl::App(url.into()))
+        .title(format!("{} - Gravity", extract_title(&content)))
+        .inner_size(state.width, state.height)
+        .min_inner_size(200.0, 150.0)
+        .resizable(true)
+        .decorations(false)
+        .always_on_top(pinned)
+        .skip_taskbar(pinned)
+        .focused(true);
+
+    let builder = if remembered {
+        builder.position(state.x, state.y)
+    } else {
+        builder.center()
+    };
+
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    let builder = builder.transparent(true).shadow(false);
+
+    let window = builder.build().map_err(|e| e.to_string())?;
+
+    // Record where the window actually opened, e.g. after centering
+    let mut state = NoteWindowState { pinned, ..state };
+    if let (Ok(position), Ok(scale)) = (window.outer_position(), window.scale_factor()) {
+        let position = position.to_logical::<f64>(scale);
+        state.x = position.x;
+        state.y = position.y;
+    }
+    states.set_note(id, state);
+    states.track(&label, id);
+
+    if let Err(e) = note_state::mark_opened(&get_notes_dir(app), id, &content) {
+        log::error!("Failed to record note opened: {}", e);
+    }
+    tray::schedule_refresh(app);
+
+    Ok(window)
+}
+
+/// Whether a window placed at `x`,`y` would still be reachable: its monitor must still be
+/// connected and its title bar must fall inside one of the current monitors
+fn is_on_screen(app: &AppHandle, x: f64, y: f64, width: f64, monitor: Option<&str>) -> bool {
+    let Ok(monitors) = app.available_monitors() else {
+        return true;
+    };
+
+    if let Some(name) = monitor {
+        if !monitors.iter().any(|m| m.name().map(String::as_str) == Some(name)) {
+            return false;
+        }
+    }
+
+    // A point a little inside the top edge, where the user would grab the window
+    let (grab_x, grab_y) = (x + (width / 2.0).min(100.0), y + 10.0);
+    monitors.iter().any(|m| {
+        let scale = m.scale_factor();
+        let origin = m.position().to_logical::<f64>(scale);
+        let size = m.size().to_logical::<f64>(scale);
+        grab_x >= origin.x && grab_x < origin.x + size.width && grab_y >= origin.y && grab_y < origin.y + size.height
+    })
+}
+
+fn monitor_name(window: &Window) -> Option<String> {
+    window.current_monitor().ok().flatten().and_then(|m| m.name().cloned())
+}
+
+/// Track the main window's placement so it can be restored on launch
+fn handle_main_window_event(window: &Window, event: &WindowEvent) {
+    let states = window.app_handle().state::<WindowStates>();
+    let scale = window.scale_factor().unwrap_or(1.0);
+    let maximized = window.is_maximized().unwrap_or(false);
+    let minimized = window.is_minimized().unwrap_or(false);
+
+    let Ok(position) = window.outer_position() else {
+        return;
+    };
+    let Ok(size) = window.inner_size() else {
+        return;
+    };
+    let position = position.to_logical::<f64>(scale);
+    let size = size.to_logical::<f64>(scale);
+
+    let geometry = MainWindowState {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized,
+        monitor: monitor_name(window),
+    };
+
+    match event {
+        // Keep the restored (un-maximized) geometry while maximized or minimized
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) if maximized || minimized => {
+            states.update_main(geometry, |state| state.maximized = maximized);
+        }
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+            states.update_main(geometry.clone(), |state| *state = geometry);
+        }
+        _ => {}
+    }
+}
+
+/// Keep the remembered placement of note windows current as they move and close
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    if window.label() == "main" {
+        handle_main_window_event(window, event);
+        return;
+    }
+
+    if !window.label().starts_with("note-") {
+        return;
+    }
+
+    let app = window.app_handle();
+    let states = app.state::<WindowStates>();
+    let scale = window.scale_factor().unwrap_or(1.0);
+
+    match event {
+        WindowEvent::Moved(position) => {
+            let position = position.to_logical::<f64>(scale);
+            let monitor = monitor_name(window);
+            states.update_note(window.label(), |state| {
+                state.x = position.x;
+                state.y = position.y;
+                state.monitor = monitor;
+            });
+        }
+        WindowEvent::Resized(size) => {
+            let size = size.to_logical::<f64>(scale);
+            // Minimizing reports a zero size; keep the last real one
+            if size.width > 0.0 && size.height > 0.0 {
+                states.update_note(window.label(), |state| {
+                    state.width = size.width;
+                    state.height = size.height;
+                });
+            }
+        }
+        WindowEvent::Destroyed => {
+            states.untrack(window.label());
+            if let Err(e) = states.save(app) {
+                log::error!("Failed to save window state: {}", e);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Remember which note windows are open and write all window state to disk
+pub fn save_session(app: &AppHandle) {
+    let states = app.state::<WindowStates>();
+    states.capture_session();
+    if let Err(e) = states.save(app) {
+        log::error!("Failed to save window session: {}", e);
+    }
+}
+
+/// Put the main window back where it was and reopen the note windows from the last session
+pub fn restore_session(app: &AppHandle) {
+    let states = app.state::<WindowStates>();
+
+    if let (Some(main), Some(window)) = (states.main(), app.get_webview_window("main")) {
+        if is_on_screen(app, main.x, main.y, main.width, main.monitor.as_deref()) {
+            let _ = window.set_size(LogicalSize::new(main.width, main.height));
+            let _ = window.set_position(LogicalPosition::new(main.x, main.y));
+        } else {
+            // The monitor is gone: keep the saved size but let it open centered
+            let _ = window.set_size(LogicalSize::new(main.width, main.height));
+            let _ = window.center();
+        }
+        if main.maximized {
+            let _ = window.maximize();
+        }
+    }
+
+    let notes_dir = get_notes_dir(app);
+    for id in states.session() {
+        if !notes_dir.join(format!("{}.md", id)).exists() {
+            continue;
+        }
+        if let Err(e) = open_note_window(app, &id, false) {
+            log::error!("Failed to restore note window {}: {}", id, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn open_note(app: AppHandle, id: String, sticky: Option<bool>) -> Result<(), String> {
+    open_note_window(&app, &id, sticky.unwrap_or(false)).map(|_| ())
+}
+
+/// Pin or unpin the calling note window: pinned windows stay on top without a taskbar entry
+#[tauri::command]
+pub async fn set_note_window_pinned(
+    window: WebviewWindow,
+    states: State<'_, WindowStates>,
+    pinned: bool,
+) -> Result<(), String> {
+    window.set_always_on_top(pinned).map_err(|e| e.to_string())?;
+    window.set_skip_taskbar(pinned).map_err(|e| e.to_string())?;
+    states.update_note(window.label(), |state| state.pinned = pinned);
+    states.save(window.app_handle())
+}
+
+/// Change the calling note window's opacity and remember it for the note
+#[tauri::command]
+pub async fn set_note_window_opacity(
+    window: WebviewWindow,
+    states: State<'_, WindowStates>,
+    opacity: f64,
+) -> Result<(), String> {
+    let opacity = opacity.clamp(0.2, 1.0);
+    // The webview draws the transparency, as at startup from the window URL
+    window
+        .eval(format!("document.documentElement.style.opacity = '{}'", opacity))
+        .map_err(|e| e.to_string())?;
+    states.update_note(window.label(), |state| state.opacity = opacity);
+    states.save(window.app_handle())
+}
@@ -0,0 +1,273 @@
+use crate::commands::write_note;
+use crate::storage::{ensure_notes_dir, NoteMeta};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Each note is written at most this often while edits keep coming in
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sent to every window once a revision of a note is on disk
+pub const NOTE_SAVED_EVENT: &str = "note-saved";
+
+/// Sent to every window when a note is rewritten outside its editor, e.g. to merge duplicate images
+pub const NOTE_REWRITTEN_EVENT: &str = "note-rewritten";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct NoteRewritten {
+    pub id: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct NoteSaved {
+    pub id: String,
+    /// Every revision up to this one is on disk
+    pub revision: u64,
+    pub note: NoteMeta,
+}
+
+struct Pending {
+    content: String,
+    revision: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Latest unwritten content per note; older versions are dropped
+    pending: HashMap<String, Pending>,
+    /// Last revision handed out per note
+    revisions: HashMap<String, u64>,
+    /// Last revision written per note
+    durable: HashMap<String, u64>,
+    last_write: HashMap<String, Instant>,
+    /// A write is in progress
+    writing: bool,
+    /// Write everything now, ignoring the rate limit
+    flushing: bool,
+}
+
+impl Inner {
+    fn next_revision(&mut self, id: &str) -> u64 {
+        let revision = self.revisions.entry(id.to_string()).or_default();
+        *revision += 1;
+        *revision
+    }
+
+    /// The pending note to write next, or how long until one is due
+    fn next_due(&self) -> Result<String, Option<Duration>> {
+        let now = Instant::now();
+        let mut wait: Option<Duration> = None;
+
+        for id in self.pending.keys() {
+            let due = match self.last_write.get(id) {
+                Some(last) if !self.flushing => *last + MIN_INTERVAL,
+                _ => return Ok(id.clone()),
+            };
+            match due.checked_duration_since(now) {
+                None => return Ok(id.clone()),
+                Some(left) => wait = Some(wait.map_or(left, |w| w.min(left))),
+            }
+        }
+
+        Err(wait)
+    }
+}
+
+/// Coalesces note saves and writes them off the command thread at a bounded rate
+#[derive(Default)]
+pub struct WriteQueue {
+    inner: Mutex<Inner>,
+    changed: Condvar,
+    /// Held for every file write, so the worker and direct saves never interleave
+    write_lock: Mutex<()>,
+}
+
+impl WriteQueue {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue `content` as the latest version of a note; returns its revision
+    pub fn enqueue(&self, id: &str, content: String) -> u64 {
+        let mut inner = self.lock();
+        let revision = inner.next_revision(id);
+        inner.pending.insert(id.to_string(), Pending { content, revision });
+        self.changed.notify_all();
+        revision
+    }
+
+    /// Write a note right away, superseding anything queued for it
+    pub fn save_now(&self, app: &AppHandle, id: &str, content: &str) -> Result<NoteMeta, String> {
+        // Taking the revision under the write lock makes it the newest one to reach the disk
+        let _write = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let revision = {
+            let mut inner = self.lock();
+            inner.pending.remove(id);
+            inner.next_revision(id)
+        };
+
+        self.write_locked(app, id, content, revision)
+    }
+
+    /// Write content that didn't come from an editor; editors showing the note switch to it
+    pub fn rewrite(&self, app: &AppHandle, id: &str, content: String) -> Result<NoteMeta, String> {
+        self.update(app, id, |_| content)
+    }
+
+    /// Change a note outside its editor. `change` gets the latest content, queued or on disk,
+    /// and its result is written under the write lock; editors showing the note switch to it.
+    pub fn update(&self, app: &AppHandle, id: &str, change: impl FnOnce(&str) -> String) -> Result<NoteMeta, String> {
+        let _write = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let (queued, revision) = {
+            let mut inner = self.lock();
+            let queued = inner.pending.remove(id).map(|pending| pending.content);
+            (queued, inner.next_revision(id))
+        };
+
+        let current = match queued {
+            Some(content) => content,
+            None => match fs::read_to_string(ensure_notes_dir(app)?.join(format!("{}.md", id))) {
+                Ok(content) => content,
+                Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e.to_string()),
+            },
+        };
+        let content = change(&current);
+
+        let note = self.write_locked(app, id, &content, revision)?;
+        let _ = app.emit(NOTE_REWRITTEN_EVENT, NoteRewritten { id: id.to_string(), content });
+        Ok(note)
+    }
+
+    /// Whether an edit to a note is waiting to be written
+    pub fn is_queued(&self, id: &str) -> bool {
+        self.lock().pending.contains_key(id)
+    }
+
+    /// Drop anything queued for a note, returning the content that won't be written
+    pub fn cancel(&self, id: &str) -> Option<String> {
+        self.lock().pending.remove(id).map(|pending| pending.content)
+    }
+
+    /// Delete a note's file with `delete`, which returns whether it did. This holds the write lock,
+    /// and once the file is gone every revision handed out so far counts as written, so a write
+    /// the worker already picked up can't bring the note back.
+    pub fn delete(&self, id: &str, delete: impl FnOnce() -> Result<bool, String>) -> Result<bool, String> {
+        let _write = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if !delete()? {
+            return Ok(false);
+        }
+
+        let mut inner = self.lock();
+        inner.pending.remove(id);
+        if let Some(revision) = inner.revisions.get(id).copied() {
+            inner.durable.insert(id.to_string(), revision);
+        }
+        Ok(true)
+    }
+
+    /// Write what is queued for a note; returns whether anything was written. The content is
+    /// taken off the queue under the write lock, so `update` always sees the latest edit.
+    fn write_pending(&self, app: &AppHandle, id: &str) -> Result<bool, String> {
+        let _write = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let pending = {
+            let mut inner = self.lock();
+            match inner.pending.remove(id) {
+                Some(pending) if inner.durable.get(id).is_some_and(|durable| *durable >= pending.revision) => None,
+                pending => pending,
+            }
+        };
+        let Some(pending) = pending else {
+            return Ok(false);
+        };
+
+        if let Err(e) = self.write_locked(app, id, &pending.content, pending.revision) {
+            // Keep it for the next pass unless a newer version replaced it meanwhile
+            self.lock().pending.entry(id.to_string()).or_insert(pending);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    fn write_locked(&self, app: &AppHandle, id: &str, content: &str, revision: u64) -> Result<NoteMeta, String> {
+        let note = write_note(&ensure_notes_dir(app)?, id, content)?;
+
+        {
+            let mut inner = self.lock();
+            inner.durable.insert(id.to_string(), revision);
+            inner.last_write.insert(id.to_string(), Instant::now());
+        }
+        self.changed.notify_all();
+
+        let saved = NoteSaved { id: id.to_string(), revision, note: note.clone() };
+        let _ = app.emit(NOTE_SAVED_EVENT, &saved);
+        Ok(note)
+    }
+
+    /// Write everything queued now; returns false if `timeout` passed first
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut inner = self.lock();
+        inner.flushing = true;
+        self.changed.notify_all();
+
+        while !inner.pending.is_empty() || inner.writing {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                inner.flushing = false;
+                return false;
+            };
+            inner = self.changed.wait_timeout(inner, left).unwrap_or_else(|e| e.into_inner()).0;
+        }
+
+        inner.flushing = false;
+        true
+    }
+}
+
+/// Start the thread that writes queued notes
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let queue = app.state::<WriteQueue>();
+
+        loop {
+            let id = {
+                let mut inner = queue.lock();
+                let id = loop {
+                    match inner.next_due() {
+                        Ok(id) => break id,
+                        Err(Some(wait)) => {
+                            inner = queue.changed.wait_timeout(inner, wait).unwrap_or_else(|e| e.into_inner()).0;
+                        }
+                        Err(None) => inner = queue.changed.wait(inner).unwrap_or_else(|e| e.into_inner()),
+                    }
+                };
+                inner.writing = true;
+                id
+            };
+
+            match queue.write_pending(&app, &id) {
+                Ok(true) => {
+                    let _ = app.emit("notes-changed", ());
+                }
+                Ok(false) => {}
+                Err(e) => log::error!("Failed to write note {}: {}", id, e),
+            }
+
+            queue.lock().writing = false;
+            queue.changed.notify_all();
+        }
+    });
+}
+
+/// Queue a note save; the `note-saved` event reports when the returned revision is on disk
+#[tauri::command]
+pub fn queue_note_write(queue: State<'_, WriteQueue>, id: String, content: String) -> u64 {
+    queue.enqueue(&id, content)
+}
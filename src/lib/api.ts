@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { NoteMeta } from './types';
+import type { AppendTarget, Attachment, CaptureRequest, DedupeReport, ImageReport, NoteMeta, NoteState, PeriodKind, PeriodicNote, RetentionReport, Settings, ShortcutAction, ShortcutInfo, SwitcherMatch, TemplateMeta, TemplateNote } from './types';
 
 export async function createNote(): Promise<NoteMeta> {
   return invoke('create_note');
@@ -9,6 +9,11 @@ export async function saveNote(id: string, content: string): Promise<NoteMeta> {
   return invoke('save_note', { id, content });
 }
 
+/** Queue a save without waiting for the disk; `note-saved` reports when the returned revision is written */
+export async function queueNoteWrite(id: string, content: string): Promise<number> {
+  return invoke('queue_note_write', { id, content });
+}
+
 export async function deleteNote(id: string): Promise<void> {
   return invoke('delete_note', { id });
 }
@@ -17,8 +22,28 @@ export async function getNote(id: string): Promise<string> {
   return invoke('get_note', { id });
 }
 
-export async function listNotes(): Promise<NoteMeta[]> {
-  return invoke('list_notes');
+export async function listNotes(includeArchived: boolean = false): Promise<NoteMeta[]> {
+  return invoke('list_notes', { includeArchived });
+}
+
+export async function setNotePinned(id: string, pinned: boolean): Promise<NoteState> {
+  return invoke('set_note_pinned', { id, pinned });
+}
+
+export async function setNoteStarred(id: string, starred: boolean): Promise<NoteState> {
+  return invoke('set_note_starred', { id, starred });
+}
+
+export async function setNoteArchived(id: string, archived: boolean): Promise<NoteState> {
+  return invoke('set_note_archived', { id, archived });
+}
+
+export async function markNoteOpened(id: string): Promise<void> {
+  return invoke('mark_note_opened', { id });
+}
+
+export async function reorderPinnedNotes(ids: string[]): Promise<void> {
+  return invoke('reorder_pinned_notes', { ids });
 }
 
 export async function deleteIfEmpty(id: string): Promise<boolean> {
@@ -28,3 +53,138 @@ export async function deleteIfEmpty(id: string): Promise<boolean> {
 export async function cleanupEmptyNotes(maxAgeMinutes: number = 15): Promise<number> {
   return invoke('cleanup_empty_notes', { maxAgeMinutes });
 }
+
+export async function listTemplates(): Promise<TemplateMeta[]> {
+  return invoke('list_templates');
+}
+
+export async function createNoteFromTemplate(name: string): Promise<TemplateNote> {
+  return invoke('create_note_from_template', { name });
+}
+
+export async function openPeriodicNote(kind: PeriodKind, date?: string): Promise<PeriodicNote> {
+  return invoke('open_periodic_note', { kind, date });
+}
+
+export async function appendToNote(target: AppendTarget, text: string): Promise<NoteMeta> {
+  return invoke('append_to_note', { target, text });
+}
+
+export async function captureReady(): Promise<CaptureRequest | null> {
+  return invoke('capture_ready');
+}
+
+export async function openNote(id: string, sticky: boolean = false): Promise<void> {
+  return invoke('open_note', { id, sticky });
+}
+
+export async function setNoteWindowPinned(pinned: boolean): Promise<void> {
+  return invoke('set_note_window_pinned', { pinned });
+}
+
+export async function setNoteWindowOpacity(opacity: number): Promise<void> {
+  return invoke('set_note_window_opacity', { opacity });
+}
+
+export async function searchNotes(query: string, limit?: number): Promise<SwitcherMatch[]> {
+  return invoke('search_notes', { query, limit });
+}
+
+export async function createNoteWithText(text: string): Promise<NoteMeta> {
+  return invoke('create_note_with_text', { text });
+}
+
+export async function getShortcuts(): Promise<ShortcutInfo[]> {
+  return invoke('get_shortcuts');
+}
+
+/** Rejects with a `ShortcutError` when the binding is invalid, taken, or can't be registered */
+export async function setShortcut(action: ShortcutAction, binding: string | null): Promise<ShortcutInfo[]> {
+  return invoke('set_shortcut', { action, binding });
+}
+
+export async function getSettings(): Promise<Settings> {
+  return invoke('get_settings');
+}
+
+/** Rejects with a `SettingsError` when a value is invalid or a shortcut can't be registered */
+export async function updateSettings(settings: Settings): Promise<Settings> {
+  return invoke('update_settings', { settings });
+}
+
+export async function exportSettings(destination: string): Promise<string> {
+  return invoke('export_settings', { destination });
+}
+
+export async function importSettings(path: string): Promise<Settings> {
+  return invoke('import_settings', { path });
+}
+
+/** Run the retention policies now; with `preview`, report what every policy would remove */
+export async function runRetention(preview: boolean = false): Promise<RetentionReport> {
+  return invoke('run_retention', { preview });
+}
+
+/** Directory of the image thumbnails, each named after its image plus `.png` */
+export async function getThumbnailsPath(): Promise<string> {
+  return invoke('get_thumbnails_path');
+}
+
+/** Every stored image with the notes using it, its size and whether it is orphaned */
+export async function getImageReport(): Promise<ImageReport> {
+  return invoke('get_image_report');
+}
+
+/** Move confirmed orphans from a report to the trash; returns the ones actually moved */
+export async function trashOrphanedImages(names: string[]): Promise<string[]> {
+  return invoke('trash_orphaned_images', { names });
+}
+
+/** Convert the clipboard to Markdown and save it as a new note with its source */
+export async function clipToNote(): Promise<NoteMeta> {
+  return invoke('clip_to_note');
+}
+
+/** Copy Markdown to the clipboard as rich HTML with the Markdown as plain text */
+export async function copyAsRichText(markdown: string, inlineImages: boolean): Promise<void> {
+  return invoke('copy_as_rich_text', { markdown, inlineImages });
+}
+
+/** Store the clipboard image and get a Markdown image link, or null when there is none */
+export async function pasteClipboardImage(): Promise<string | null> {
+  return invoke('paste_clipboard_image');
+}
+
+/** Store a file under its original name (made unique) and get a link for the note */
+export async function saveAttachment(filename: string, data: number[]): Promise<Attachment> {
+  return invoke('save_attachment', { filename, data });
+}
+
+/** Copy a file from disk into the attachments */
+export async function attachFile(path: string): Promise<Attachment> {
+  return invoke('attach_file', { path });
+}
+
+/** Open an attachment with the system's default application */
+export async function openAttachment(name: string): Promise<void> {
+  return invoke('open_attachment', { name });
+}
+
+/** Move confirmed orphaned attachments from a report to the trash */
+export async function trashOrphanedAttachments(names: string[]): Promise<string[]> {
+  return invoke('trash_orphaned_attachments', { names });
+}
+
+/** Merge identical stored images into one file each, rewriting the notes that use them */
+export async function dedupeStoredImages(): Promise<DedupeReport> {
+  return invoke('dedupe_stored_images');
+}
+
+/** Tell the backend this window saved everything after `app-shutdown` */
+export async function shutdownFlushed(): Promise<void> {
+  return invoke('shutdown_flushed');
+}
+
+export async function captureNoteCreated(id: string): Promise<void> {
+  return invoke('capture_note_created', { id });
+}
@@ -1,36 +1,52 @@
 import { writable } from 'svelte/store';
-import { Store } from '@tauri-apps/plugin-store';
-
-export interface Settings {
-  theme: 'system' | 'light' | 'dark';
-  editorFontSize: number;
-  newNoteShortcut: string;
-  mainWindowShortcut: string;
-}
+import { listen } from '@tauri-apps/api/event';
+import { getSettings, updateSettings } from '../api';
+import type { Settings } from '../types';
 
 const defaultSettings: Settings = {
+  version: 1,
   theme: 'system',
-  editorFontSize: 15,
-  newNoteShortcut: 'Ctrl+Alt+N',
-  mainWindowShortcut: 'Ctrl+Alt+G',
+  editor_font_size: 15,
+  shortcuts: {},
+  retention: {
+    interval_minutes: 60,
+    empty_notes: 'on',
+    empty_note_minutes: 15,
+    trash: 'on',
+    trash_days: 30,
+    orphaned_images: 'dry_run',
+    orphaned_image_days: 7,
+    history: 'on',
+  },
+  images: {
+    max_dimension: 2560,
+    strip_metadata: true,
+    jpeg_quality: 85,
+  },
+  periodic: {
+    daily_template: 'daily',
+    weekly_template: 'weekly',
+    monthly_template: 'monthly',
+  },
 };
 
+// The backend owns the settings; this store mirrors them for the UI
 export const settings = writable<Settings>(defaultSettings);
 
-let store: Store | null = null;
+let listening = false;
 
-export async function loadSettings() {
-  store = await Store.load('settings.json');
-  const saved = await store.get<Settings>('settings');
-  if (saved) {
-    settings.set({ ...defaultSettings, ...saved });
+export async function loadSettings(): Promise<Settings> {
+  const loaded = await getSettings();
+  settings.set(loaded);
+
+  if (!listening) {
+    listening = true;
+    await listen<Settings>('settings-changed', (event) => settings.set(event.payload));
   }
+  return loaded;
 }
 
+/** Rejects with a `SettingsError` when the backend refuses the new settings */
 export async function saveSettings(newSettings: Settings) {
-  settings.set(newSettings);
-  if (store) {
-    await store.set('settings', newSettings);
-    await store.save();
-  }
+  settings.set(await updateSettings(newSettings));
 }
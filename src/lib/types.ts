@@ -7,6 +7,19 @@ export interface NoteMeta {
   modified_at: string;
   word_count: number;
   char_count: number;
+  pinned: boolean;
+  starred: boolean;
+  archived: boolean;
+  last_opened: string | null;
+  /** First image the note refers to */
+  image: string | null;
+}
+
+export interface NoteState {
+  pinned: boolean;
+  pin_order?: number;
+  starred: boolean;
+  archived: boolean;
 }
 
 export interface EditorStats {
@@ -15,3 +28,162 @@ export interface EditorStats {
   line: number;
   column: number;
 }
+
+export interface TemplateMeta {
+  name: string;
+  shortcut: string | null;
+}
+
+export interface TemplateNote {
+  note: NoteMeta;
+  content: string;
+  cursor: number | null;
+}
+
+export type PeriodKind = 'daily' | 'weekly' | 'monthly';
+
+export interface PeriodicNote {
+  note: NoteMeta;
+  created: boolean;
+}
+
+export type AppendTarget = 'inbox' | 'today';
+
+export type CaptureRequest =
+  | { mode: 'new' }
+  | { mode: 'template'; name: string }
+  | { mode: 'append'; target: AppendTarget }
+  | { mode: 'existing'; note: string };
+
+/** Payload of `note-saved`: every revision up to `revision` is on disk */
+export interface NoteSaved {
+  id: string;
+  revision: number;
+  note: NoteMeta;
+}
+
+/** Payload of `note-rewritten`: the note was rewritten outside its editor */
+export interface NoteRewritten {
+  id: string;
+  content: string;
+}
+
+export interface SwitcherMatch {
+  note: NoteMeta;
+  score: number;
+  title_positions: number[];
+  snippet: string | null;
+}
+
+export type ShortcutAction =
+  | 'new_note'
+  | 'main_window'
+  | 'clip_to_markdown'
+  | 'clip_to_note'
+  | 'append_inbox'
+  | 'capture_selection'
+  | 'today_note'
+  | 'quick_switcher'
+  | 'image_to_note';
+
+export type ShortcutError =
+  | { kind: 'invalid'; binding: string; reason: string }
+  | { kind: 'conflict'; binding: string; owner: string }
+  | { kind: 'in_use'; binding: string }
+  | { kind: 'failed'; binding: string; reason: string }
+  | { kind: 'storage'; reason: string };
+
+export interface ShortcutInfo {
+  action: ShortcutAction;
+  binding: string | null;
+  /** null for actions that start out unbound */
+  default_binding: string | null;
+  error: ShortcutError | null;
+}
+
+export interface Settings {
+  version: number;
+  theme: 'system' | 'light' | 'dark';
+  editor_font_size: number;
+  shortcuts: Partial<Record<ShortcutAction, string | null>>;
+  retention: RetentionSettings;
+  images: ImageSettings;
+  periodic: PeriodicSettings;
+}
+
+export interface PeriodicSettings {
+  /** Template names; null, or a template that doesn't exist, gives a plain dated heading */
+  daily_template: string | null;
+  weekly_template: string | null;
+  monthly_template: string | null;
+}
+
+export interface ImageSettings {
+  /** Larger images are scaled down to fit; null keeps the original size */
+  max_dimension: number | null;
+  strip_metadata: boolean;
+  jpeg_quality: number;
+}
+
+export type PolicyMode = 'off' | 'dry_run' | 'on';
+
+export interface RetentionSettings {
+  interval_minutes: number;
+  empty_notes: PolicyMode;
+  empty_note_minutes: number;
+  trash: PolicyMode;
+  trash_days: number;
+  orphaned_images: PolicyMode;
+  orphaned_image_days: number;
+  history: PolicyMode;
+}
+
+export interface PolicyReport {
+  mode: PolicyMode;
+  items: string[];
+}
+
+export interface RetentionReport {
+  empty_notes: PolicyReport;
+  trash: PolicyReport;
+  orphaned_images: PolicyReport;
+  orphaned_attachments: PolicyReport;
+  history: PolicyReport;
+}
+
+export interface FileUsage {
+  name: string;
+  size: number;
+  notes: string[];
+  trashed_notes: number;
+  orphaned: boolean;
+}
+
+export interface ImageReport {
+  images: FileUsage[];
+  attachments: FileUsage[];
+  total_size: number;
+  orphaned_size: number;
+  grace_days: number;
+}
+
+export interface Attachment {
+  name: string;
+  size: number;
+  mime: string;
+  /** Link to insert into the note */
+  markdown: string;
+}
+
+export interface DedupeReport {
+  /** Duplicate file name -> the identical image now used in its place */
+  merged: Record<string, string>;
+  rewritten_notes: string[];
+  freed_bytes: number;
+}
+
+export type SettingsError =
+  | { kind: 'invalid'; field: string; message: string }
+  | { kind: 'shortcut'; error: ShortcutError }
+  | { kind: 'unsupported_version'; version: number }
+  | { kind: 'io'; message: string };